@@ -0,0 +1,85 @@
+use crate::Args;
+use crate::config::Manifest;
+use crate::plot;
+use anyhow::{Result, bail};
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Renders every plot in a batch manifest concurrently and prints a summary.
+///
+/// Flags given explicitly on the command line apply to every plot. Fails only
+/// after all plots have finished, if any of them failed. Image decoding across
+/// all plots shares the same `workers` limit.
+pub async fn run(
+    manifest: &Path,
+    args: &Args,
+    matches: &ArgMatches,
+    workers: &Arc<Semaphore>,
+) -> Result<()> {
+    let plots = Manifest::load(manifest)?.plots;
+    let total = plots.len();
+    if total > 1 && matches.value_source("output") == Some(ValueSource::CommandLine) {
+        bail!("--output cannot be used with a manifest of {total} plots, since every plot would write to it");
+    }
+
+    let plot_args: Vec<_> = plots
+        .into_iter()
+        .map(|plot| {
+            let mut plot_args = args.clone();
+            plot.apply(&mut plot_args, matches);
+            plot_args
+        })
+        .collect();
+    check_outputs(&plot_args)?;
+
+    let tasks: Vec<_> = plot_args
+        .into_iter()
+        .map(|plot_args| {
+            let output = plot_args.output.clone();
+            let workers = Arc::clone(workers);
+            let task = tokio::spawn(async move {
+                plot::create_plot(plot_args.into_plot_config()?, &workers).await
+            });
+            (output, task)
+        })
+        .collect();
+
+    let mut failed = 0;
+    for (output, task) in tasks {
+        match task.await.map_err(anyhow::Error::from).and_then(|result| result) {
+            Ok(()) => println!("ok      {}", output.display()),
+            Err(err) => {
+                failed += 1;
+                eprintln!("FAILED  {}: {err:#}", output.display());
+            }
+        }
+    }
+
+    println!("{} of {total} plots rendered", total - failed);
+    if failed > 0 {
+        bail!("{failed} of {total} plots failed");
+    }
+    Ok(())
+}
+
+/// Fails if two plots would write to the same file, as concurrent renders
+/// would overwrite each other.
+fn check_outputs(plots: &[Args]) -> Result<()> {
+    let mut seen = HashMap::new();
+    for (index, plot) in plots.iter().enumerate() {
+        let output = std::path::absolute(&plot.output).unwrap_or_else(|_| plot.output.clone());
+        if let Some(first) = seen.insert(output, index) {
+            bail!(
+                "Plots {} and {} both write to {}; give each plot its own output",
+                first + 1,
+                index + 1,
+                plot.output.display()
+            );
+        }
+    }
+    Ok(())
+}
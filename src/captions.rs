@@ -0,0 +1,46 @@
+use crate::loader::is_empty_slot;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Where captions are drawn relative to their image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaptionPosition {
+    /// In a band of its own beneath each image
+    #[default]
+    Below,
+    /// Over the bottom edge of each image
+    Overlay,
+}
+
+impl FromStr for CaptionPosition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "below" => Ok(Self::Below),
+            "overlay" => Ok(Self::Overlay),
+            _ => Err(format!("Invalid caption position: {s}. Valid values are: below, overlay")),
+        }
+    }
+}
+
+/// Expands a caption template for every image. Supported placeholders are
+/// `{filename}`, `{stem}`, `{path}` and `{index}` (1-based). Empty slots get
+/// no caption.
+pub fn from_template(template: &str, images: &[PathBuf]) -> Vec<String> {
+    images
+        .iter()
+        .enumerate()
+        .map(|(index, path)| {
+            if is_empty_slot(path) {
+                return String::new();
+            }
+            let lossy = |part: Option<&std::ffi::OsStr>| part.unwrap_or_default().to_string_lossy().into_owned();
+            template
+                .replace("{filename}", &lossy(path.file_name()))
+                .replace("{stem}", &lossy(path.file_stem()))
+                .replace("{path}", &path.to_string_lossy())
+                .replace("{index}", &(index + 1).to_string())
+        })
+        .collect()
+}
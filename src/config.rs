@@ -0,0 +1,492 @@
+use crate::captions::CaptionPosition;
+use crate::fit::{CellSize, FitMode, Resample};
+use crate::grid::FillOrder;
+use crate::inputs::SortKey;
+use crate::labels::{LabelGroup, PathSegment};
+use crate::layout::{Margin, Padding};
+use crate::lines::LineStyle;
+use crate::loader::{MissingPolicy, is_empty_slot};
+use crate::output::{OutputFormat, PngCompression};
+use crate::text::LabelAlignment;
+use crate::theme::{Color, ThemeName};
+use crate::Args;
+use anyhow::{Context, Result, bail};
+use clap::ArgMatches;
+use clap::parser::ValueSource;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+/// A plot description loaded from a TOML, YAML or JSON file.
+///
+/// Every field mirrors the command-line flag of the same name and is optional.
+/// Relative paths are resolved against the directory containing the file, so a
+/// checked-in description renders the same figure from any working directory.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlotFile {
+    pub images: Option<Vec<PathBuf>>,
+    pub images_from: Option<PathBuf>,
+    pub recursive: Option<bool>,
+    pub sort: Option<SortKey>,
+    pub output: Option<PathBuf>,
+    pub format: Option<OutputFormat>,
+    pub quality: Option<u8>,
+    pub png_compression: Option<PngCompression>,
+    pub rows: Option<u32>,
+    pub cols: Option<u32>,
+    pub fill_order: Option<FillOrder>,
+    pub row_labels: Option<Vec<String>>,
+    pub column_labels: Option<Vec<String>>,
+    pub row_labels_file: Option<PathBuf>,
+    pub column_labels_file: Option<PathBuf>,
+    pub label_delimiter: Option<String>,
+    pub column_groups: Option<Vec<LabelGroup>>,
+    pub row_groups: Option<Vec<LabelGroup>>,
+    pub auto_labels: Option<bool>,
+    pub column_label_source: Option<PathSegment>,
+    pub row_label_source: Option<PathSegment>,
+    pub column_label_alignment: Option<LabelAlignment>,
+    pub row_label_alignment: Option<LabelAlignment>,
+    pub debug: Option<bool>,
+    pub top_padding: Option<Padding>,
+    pub left_padding: Option<Padding>,
+    pub column_group_padding: Option<Padding>,
+    pub row_group_padding: Option<Padding>,
+    pub label_inset: Option<u32>,
+    pub gap_x: Option<u32>,
+    pub gap_y: Option<u32>,
+    pub margin: Option<Margin>,
+    pub cell_border: Option<u32>,
+    pub grid_lines: Option<u32>,
+    pub header_line: Option<u32>,
+    pub line_style: Option<LineStyle>,
+    pub captions: Option<Vec<String>>,
+    pub captions_file: Option<PathBuf>,
+    pub caption_template: Option<String>,
+    pub caption_alignment: Option<LabelAlignment>,
+    pub caption_position: Option<CaptionPosition>,
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    pub footer: Option<String>,
+    pub title_alignment: Option<LabelAlignment>,
+    pub subtitle_alignment: Option<LabelAlignment>,
+    pub footer_alignment: Option<LabelAlignment>,
+    pub font: Option<PathBuf>,
+    pub fallback_font: Option<Vec<PathBuf>>,
+    pub font_size: Option<f32>,
+    pub column_label_font_size: Option<f32>,
+    pub row_label_font_size: Option<f32>,
+    pub caption_font_size: Option<f32>,
+    pub title_font_size: Option<f32>,
+    pub subtitle_font_size: Option<f32>,
+    pub footer_font_size: Option<f32>,
+    pub theme: Option<ThemeName>,
+    pub background: Option<Color>,
+    pub text_color: Option<Color>,
+    pub grid_line_color: Option<Color>,
+    pub transparent: Option<bool>,
+    pub cell_size: Option<CellSize>,
+    pub fit: Option<FitMode>,
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    pub max_pixels: Option<u64>,
+    pub resample: Option<Resample>,
+    pub missing: Option<MissingPolicy>,
+    pub max_label_lines: Option<NonZeroUsize>,
+    pub lenient: Option<bool>,
+}
+
+impl PlotFile {
+    /// Reads a plot description, choosing the format from the file extension.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file: Self = read_file(path)?;
+        Ok(file.relative_to(base_dir(path)))
+    }
+
+    fn relative_to(mut self, base: &Path) -> Self {
+        if let Some(images) = &mut self.images {
+            for image in images.iter_mut().filter(|image| !is_empty_slot(image)) {
+                *image = base.join(&*image);
+            }
+        }
+        self.images_from = self
+            .images_from
+            .map(|path| if path == Path::new("-") { path } else { base.join(path) });
+        self.output = self.output.map(|output| base.join(output));
+        self.row_labels_file = self.row_labels_file.map(|path| base.join(path));
+        self.column_labels_file = self.column_labels_file.map(|path| base.join(path));
+        self.captions_file = self.captions_file.map(|path| base.join(path));
+        self.font = self.font.map(|path| base.join(path));
+        if let Some(fonts) = &mut self.fallback_font {
+            for font in fonts {
+                *font = base.join(&*font);
+            }
+        }
+        self
+    }
+
+    /// Copies every value from the file into `args` unless the corresponding
+    /// flag was given explicitly on the command line.
+    pub fn apply(mut self, args: &mut Args, matches: &ArgMatches) {
+        let explicit = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+        // Label files take precedence over label lists, so a file named in the
+        // plot description must not shadow labels given on the command line.
+        if explicit("row_labels") {
+            self.row_labels_file = None;
+        }
+        if explicit("column_labels") {
+            self.column_labels_file = None;
+        }
+        // Likewise for captions, where a template beats a file and a file beats a list.
+        if explicit("captions") {
+            self.caption_template = None;
+            self.captions_file = None;
+        }
+        if explicit("caption_template") {
+            self.captions_file = None;
+        }
+        if explicit("captions_file") {
+            self.caption_template = None;
+        }
+
+        self.merge_inputs(args, &explicit);
+        self.merge_labels(args, &explicit);
+        self.merge_text(args, &explicit);
+        self.merge_style(args, &explicit);
+    }
+
+    /// Merges the images, grid shape, sizing and output settings.
+    fn merge_inputs(&mut self, args: &mut Args, explicit: &impl Fn(&str) -> bool) {
+        merge(&mut args.images, self.images.take(), explicit("images"));
+        merge(&mut args.images_from, self.images_from.take().map(Some), explicit("images_from"));
+        merge(&mut args.recursive, self.recursive.take(), explicit("recursive"));
+        merge(&mut args.sort, self.sort.take(), explicit("sort"));
+        merge(&mut args.output, self.output.take(), explicit("output"));
+        merge(&mut args.format, self.format.take().map(Some), explicit("format"));
+        merge(&mut args.quality, self.quality.take().map(Some), explicit("quality"));
+        merge(&mut args.png_compression, self.png_compression.take(), explicit("png_compression"));
+        merge(&mut args.rows, self.rows.take().map(Some), explicit("rows"));
+        merge(&mut args.cols, self.cols.take().map(Some), explicit("cols"));
+        merge(&mut args.fill_order, self.fill_order.take(), explicit("fill_order"));
+        merge(&mut args.cell_size, self.cell_size.take().map(Some), explicit("cell_size"));
+        merge(&mut args.fit, self.fit.take(), explicit("fit"));
+        merge(&mut args.max_width, self.max_width.take().map(Some), explicit("max_width"));
+        merge(&mut args.max_height, self.max_height.take().map(Some), explicit("max_height"));
+        merge(&mut args.max_pixels, self.max_pixels.take().map(Some), explicit("max_pixels"));
+        merge(&mut args.resample, self.resample.take(), explicit("resample"));
+        merge(&mut args.missing, self.missing.take(), explicit("missing"));
+        merge(&mut args.lenient, self.lenient.take(), explicit("lenient"));
+    }
+
+    /// Merges the row and column labels and the bands they are drawn in.
+    fn merge_labels(&mut self, args: &mut Args, explicit: &impl Fn(&str) -> bool) {
+        merge(&mut args.row_labels, self.row_labels.take(), explicit("row_labels"));
+        merge(&mut args.column_labels, self.column_labels.take(), explicit("column_labels"));
+        merge(&mut args.row_labels_file, self.row_labels_file.take().map(Some), explicit("row_labels_file"));
+        merge(
+            &mut args.column_labels_file,
+            self.column_labels_file.take().map(Some),
+            explicit("column_labels_file"),
+        );
+        merge(&mut args.label_delimiter, self.label_delimiter.take().map(Some), explicit("label_delimiter"));
+        merge(&mut args.column_groups, self.column_groups.take(), explicit("column_groups"));
+        merge(&mut args.row_groups, self.row_groups.take(), explicit("row_groups"));
+        merge(&mut args.auto_labels, self.auto_labels.take(), explicit("auto_labels"));
+        merge(
+            &mut args.column_label_source,
+            self.column_label_source.take(),
+            explicit("column_label_source"),
+        );
+        merge(&mut args.row_label_source, self.row_label_source.take(), explicit("row_label_source"));
+        merge(
+            &mut args.column_label_alignment,
+            self.column_label_alignment.take(),
+            explicit("column_label_alignment"),
+        );
+        merge(
+            &mut args.row_label_alignment,
+            self.row_label_alignment.take(),
+            explicit("row_label_alignment"),
+        );
+        merge(&mut args.top_padding, self.top_padding.take(), explicit("top_padding"));
+        merge(&mut args.left_padding, self.left_padding.take(), explicit("left_padding"));
+        merge(
+            &mut args.column_group_padding,
+            self.column_group_padding.take(),
+            explicit("column_group_padding"),
+        );
+        merge(&mut args.row_group_padding, self.row_group_padding.take(), explicit("row_group_padding"));
+        merge(&mut args.label_inset, self.label_inset.take(), explicit("label_inset"));
+        merge(&mut args.max_label_lines, self.max_label_lines.take().map(Some), explicit("max_label_lines"));
+    }
+
+    /// Merges the captions, headings and fonts.
+    fn merge_text(&mut self, args: &mut Args, explicit: &impl Fn(&str) -> bool) {
+        merge(&mut args.captions, self.captions.take(), explicit("captions"));
+        merge(&mut args.captions_file, self.captions_file.take().map(Some), explicit("captions_file"));
+        merge(
+            &mut args.caption_template,
+            self.caption_template.take().map(Some),
+            explicit("caption_template"),
+        );
+        merge(&mut args.caption_alignment, self.caption_alignment.take(), explicit("caption_alignment"));
+        merge(&mut args.caption_position, self.caption_position.take(), explicit("caption_position"));
+        merge(&mut args.title, self.title.take().map(Some), explicit("title"));
+        merge(&mut args.subtitle, self.subtitle.take().map(Some), explicit("subtitle"));
+        merge(&mut args.footer, self.footer.take().map(Some), explicit("footer"));
+        merge(&mut args.title_alignment, self.title_alignment.take(), explicit("title_alignment"));
+        merge(
+            &mut args.subtitle_alignment,
+            self.subtitle_alignment.take(),
+            explicit("subtitle_alignment"),
+        );
+        merge(&mut args.footer_alignment, self.footer_alignment.take(), explicit("footer_alignment"));
+        merge(&mut args.font, self.font.take().map(Some), explicit("font"));
+        merge(&mut args.fallback_font, self.fallback_font.take(), explicit("fallback_font"));
+        merge(&mut args.font_size, self.font_size.take(), explicit("font_size"));
+        merge(
+            &mut args.column_label_font_size,
+            self.column_label_font_size.take().map(Some),
+            explicit("column_label_font_size"),
+        );
+        merge(
+            &mut args.row_label_font_size,
+            self.row_label_font_size.take().map(Some),
+            explicit("row_label_font_size"),
+        );
+        merge(&mut args.caption_font_size, self.caption_font_size.take(), explicit("caption_font_size"));
+        merge(&mut args.title_font_size, self.title_font_size.take(), explicit("title_font_size"));
+        merge(&mut args.subtitle_font_size, self.subtitle_font_size.take(), explicit("subtitle_font_size"));
+        merge(&mut args.footer_font_size, self.footer_font_size.take(), explicit("footer_font_size"));
+    }
+
+    /// Merges the colors, lines and spacing.
+    fn merge_style(&mut self, args: &mut Args, explicit: &impl Fn(&str) -> bool) {
+        merge(&mut args.debug, self.debug.take(), explicit("debug"));
+        merge(&mut args.gap_x, self.gap_x.take(), explicit("gap_x"));
+        merge(&mut args.gap_y, self.gap_y.take(), explicit("gap_y"));
+        merge(&mut args.margin, self.margin.take(), explicit("margin"));
+        merge(&mut args.cell_border, self.cell_border.take(), explicit("cell_border"));
+        merge(&mut args.grid_lines, self.grid_lines.take(), explicit("grid_lines"));
+        merge(&mut args.header_line, self.header_line.take(), explicit("header_line"));
+        merge(&mut args.line_style, self.line_style.take(), explicit("line_style"));
+        merge(&mut args.theme, self.theme.take(), explicit("theme"));
+        merge(&mut args.background, self.background.take().map(Some), explicit("background"));
+        merge(&mut args.text_color, self.text_color.take().map(Some), explicit("text_color"));
+        merge(&mut args.grid_line_color, self.grid_line_color.take().map(Some), explicit("grid_line_color"));
+        merge(&mut args.transparent, self.transparent.take(), explicit("transparent"));
+    }
+}
+
+/// A list of plot descriptions rendered together with `--batch`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(rename = "plot")]
+    pub plots: Vec<PlotFile>,
+}
+
+impl Manifest {
+    /// Reads a batch manifest, choosing the format from the file extension.
+    pub fn load(path: &Path) -> Result<Self> {
+        let manifest: Self = read_file(path)?;
+        let base = base_dir(path);
+        Ok(Self {
+            plots: manifest
+                .plots
+                .into_iter()
+                .map(|plot| plot.relative_to(base))
+                .collect(),
+        })
+    }
+}
+
+fn read_file<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "toml" => toml::from_str(&text).map_err(anyhow::Error::from),
+        "yaml" | "yml" => serde_yaml_ng::from_str(&text).map_err(anyhow::Error::from),
+        "json" => serde_json::from_str(&text).map_err(anyhow::Error::from),
+        _ => bail!(
+            "Unsupported config format: {}. Use a .toml, .yaml, .yml or .json file",
+            path.display()
+        ),
+    }
+    .with_context(|| format!("Failed to parse config file {}", path.display()))
+}
+
+fn base_dir(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new(""))
+}
+
+fn merge<T>(target: &mut T, value: Option<T>, explicit: bool) {
+    if let Some(value) = value.filter(|_| !explicit) {
+        *target = value;
+    }
+}
+
+/// Deserializes a type from the same strings accepted on the command line.
+macro_rules! deserialize_from_str {
+    ($($ty:ty),* $(,)?) => {$(
+        impl<'de> Deserialize<'de> for $ty {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                String::deserialize(deserializer)?
+                    .parse()
+                    .map_err(serde::de::Error::custom)
+            }
+        }
+    )*};
+}
+
+/// A value written either as a number of pixels or as a string accepted by the
+/// command line.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PixelsOrText {
+    Pixels(u32),
+    Text(String),
+}
+
+impl PixelsOrText {
+    fn parse<'de, T, D>(deserializer: D, pixels: impl FnOnce(u32) -> T) -> Result<T, D::Error>
+    where
+        T: std::str::FromStr<Err = String>,
+        D: Deserializer<'de>,
+    {
+        match Self::deserialize(deserializer)? {
+            Self::Pixels(value) => Ok(pixels(value)),
+            Self::Text(text) => text.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// Padding is written as a number of pixels or as the string `auto`.
+impl<'de> Deserialize<'de> for Padding {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        PixelsOrText::parse(deserializer, Self::Fixed)
+    }
+}
+
+/// A margin is written as a number of pixels for every side or as a string
+/// accepted by `--margin`.
+impl<'de> Deserialize<'de> for Margin {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        PixelsOrText::parse(deserializer, Self::uniform)
+    }
+}
+
+deserialize_from_str!(
+    LabelAlignment,
+    CaptionPosition,
+    PathSegment,
+    Color,
+    ThemeName,
+    OutputFormat,
+    PngCompression,
+    CellSize,
+    FitMode,
+    Resample,
+    LineStyle,
+    FillOrder,
+    MissingPolicy,
+    LabelGroup,
+    SortKey,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, FromArgMatches};
+
+    /// Applies the TOML plot description to the given command line.
+    fn apply(toml: &str, flags: &[&str]) -> Args {
+        let command_line = ["xyplot", "--config", "plot.toml"].iter().chain(flags);
+        let matches = Args::command().try_get_matches_from(command_line).unwrap();
+        let mut args = Args::from_arg_matches(&matches).unwrap();
+        toml::from_str::<PlotFile>(toml).unwrap().apply(&mut args, &matches);
+        args
+    }
+
+    #[test]
+    fn command_line_overrides_the_file() {
+        let args = apply("rows = 2\ncols = 4\ntheme = \"dark\"", &["--rows", "3"]);
+        assert_eq!(args.rows, Some(3));
+        assert_eq!(args.cols, Some(4));
+        assert_eq!(args.theme, ThemeName::Dark);
+    }
+
+    #[test]
+    fn label_list_overrides_label_file() {
+        let args = apply("row_labels_file = \"rows.txt\"", &["--row-labels", "a", "b"]);
+        assert_eq!(args.row_labels, ["a", "b"]);
+        assert_eq!(args.row_labels_file, None);
+
+        let args = apply("row_labels_file = \"rows.txt\"\ncolumn_labels = [\"x\"]", &[]);
+        assert_eq!(args.row_labels_file, Some(PathBuf::from("rows.txt")));
+        assert_eq!(args.column_labels, ["x"]);
+    }
+
+    #[test]
+    fn caption_flags_override_every_caption_source_in_the_file() {
+        let file = "caption_template = \"{stem}\"\ncaptions_file = \"captions.txt\"";
+
+        let args = apply(file, &["--captions", "a"]);
+        assert_eq!(args.captions, ["a"]);
+        assert_eq!((args.caption_template, args.captions_file), (None, None));
+
+        let args = apply(file, &["--caption-template", "{name}"]);
+        assert_eq!(args.caption_template.as_deref(), Some("{name}"));
+        assert_eq!(args.captions_file, None);
+
+        let args = apply(file, &["--captions-file", "other.txt"]);
+        assert_eq!(args.captions_file, Some(PathBuf::from("other.txt")));
+        assert_eq!(args.caption_template, None);
+    }
+
+    #[test]
+    fn resolves_paths_against_the_file_directory() {
+        let file: PlotFile = toml::from_str(
+            "images = [\"a.png\", \"_\", \"-\", \"/abs/b.png\"]\n\
+             images_from = \"-\"\n\
+             output = \"out.png\"\n\
+             captions_file = \"captions.txt\"",
+        )
+        .unwrap();
+        let file = file.relative_to(Path::new("plots"));
+        let images = file.images.unwrap();
+        assert_eq!(
+            images,
+            [
+                PathBuf::from("plots/a.png"),
+                PathBuf::from("_"),
+                PathBuf::from("-"),
+                PathBuf::from("/abs/b.png"),
+            ]
+        );
+        assert_eq!(file.images_from, Some(PathBuf::from("-")));
+        assert_eq!(file.output, Some(PathBuf::from("plots/out.png")));
+        assert_eq!(file.captions_file, Some(PathBuf::from("plots/captions.txt")));
+    }
+
+    #[test]
+    fn reads_padding_and_margins_as_pixels_or_text() {
+        let file: PlotFile = toml::from_str("margin = 8\ntop_padding = 12\nleft_padding = \"auto\"").unwrap();
+        assert_eq!(file.margin, Some(Margin::uniform(8)));
+        assert_eq!(file.top_padding, Some(Padding::Fixed(12)));
+        assert_eq!(file.left_padding, Some(Padding::Auto));
+
+        let file: PlotFile = toml::from_str("margin = \"1,2,3,4\"").unwrap();
+        assert_eq!(file.margin.map(|margin| (margin.top, margin.left)), Some((1, 4)));
+        assert!(toml::from_str::<PlotFile>("top_padding = \"wide\"").is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use image::imageops::{self, FilterType};
+use image::RgbaImage;
+use std::str::FromStr;
+
+/// The size every image cell is normalized to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CellSize {
+    /// The smallest cell that holds every image at its original size.
+    pub fn enclosing<'a>(images: impl IntoIterator<Item = &'a RgbaImage>) -> Self {
+        images.into_iter().fold(Self { width: 0, height: 0 }, |size, image| Self {
+            width: size.width.max(image.width()),
+            height: size.height.max(image.height()),
+        })
+    }
+}
+
+impl FromStr for CellSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid cell size: {s}. Expected WIDTHxHEIGHT, e.g. 512x512");
+        let lower = s.to_lowercase();
+        let (width, height) = lower.split_once('x').ok_or_else(invalid)?;
+        match (width.trim().parse(), height.trim().parse()) {
+            (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok(Self { width, height }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Wrapper type for `FilterType` to implement `FromStr`
+#[derive(Debug, Clone, Copy)]
+pub struct Resample(pub FilterType);
+
+impl FromStr for Resample {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "nearest" => Ok(Self(FilterType::Nearest)),
+            "triangle" => Ok(Self(FilterType::Triangle)),
+            "catmull-rom" => Ok(Self(FilterType::CatmullRom)),
+            "gaussian" => Ok(Self(FilterType::Gaussian)),
+            "lanczos3" => Ok(Self(FilterType::Lanczos3)),
+            _ => Err(format!(
+                "Invalid resampling filter: {s}. Valid values are: nearest, triangle, catmull-rom, gaussian, lanczos3"
+            )),
+        }
+    }
+}
+
+/// How an image is normalized into a cell that differs from its own size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FitMode {
+    /// Scale to fit inside the cell, letterboxing with the background
+    Contain,
+    /// Scale to fill the cell, cropping the overflow around the center
+    Cover,
+    /// Scale to the cell size, ignoring the aspect ratio
+    Stretch,
+    /// Keep the original size, cropping around the center if too large
+    #[default]
+    None,
+}
+
+impl FromStr for FitMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "contain" => Ok(Self::Contain),
+            "cover" => Ok(Self::Cover),
+            "stretch" => Ok(Self::Stretch),
+            "none" => Ok(Self::None),
+            _ => Err(format!(
+                "Invalid fit mode: {s}. Valid values are: contain, cover, stretch, none"
+            )),
+        }
+    }
+}
+
+/// How images are normalized into their cells.
+#[derive(Debug, Clone, Copy)]
+pub struct Fit {
+    pub cell: CellSize,
+    pub mode: FitMode,
+    /// Factor by which cells were shrunk to respect output size limits. Images
+    /// kept at their original size are shrunk by the same factor.
+    pub scale: f64,
+    pub filter: FilterType,
+}
+
+/// Normalizes an image into the cell. The result never exceeds the cell; it
+/// is smaller only for `Contain` and `None`, and is then centered in the cell.
+pub fn fit_image(image: RgbaImage, fit: Fit) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let cell = fit.cell;
+    let scale_x = f64::from(cell.width) / f64::from(width.max(1));
+    let scale_y = f64::from(cell.height) / f64::from(height.max(1));
+
+    match fit.mode {
+        FitMode::Contain => scale(&image, scale_x.min(scale_y), fit.filter),
+        FitMode::Cover => crop_center(scale(&image, scale_x.max(scale_y), fit.filter), cell),
+        FitMode::Stretch => imageops::resize(&image, cell.width, cell.height, fit.filter),
+        FitMode::None if fit.scale < 1.0 => crop_center(scale(&image, fit.scale, fit.filter), cell),
+        FitMode::None => crop_center(image, cell),
+    }
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn scale(image: &RgbaImage, factor: f64, filter: FilterType) -> RgbaImage {
+    let size = |length: u32| ((f64::from(length) * factor).round() as u32).max(1);
+    imageops::resize(image, size(image.width()), size(image.height()), filter)
+}
+
+fn crop_center(image: RgbaImage, cell: CellSize) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    if width <= cell.width && height <= cell.height {
+        return image;
+    }
+    let crop_width = width.min(cell.width);
+    let crop_height = height.min(cell.height);
+    imageops::crop_imm(
+        &image,
+        (width - crop_width) / 2,
+        (height - crop_height) / 2,
+        crop_width,
+        crop_height,
+    )
+    .to_image()
+}
@@ -0,0 +1,115 @@
+use anyhow::{Context, Result, bail};
+use std::str::FromStr;
+
+/// The order in which images are placed into the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FillOrder {
+    /// Left to right, then top to bottom
+    #[default]
+    Row,
+    /// Top to bottom, then left to right
+    Column,
+}
+
+impl FromStr for FillOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "row" => Ok(Self::Row),
+            "column" => Ok(Self::Column),
+            _ => Err(format!("Invalid fill order: {s}. Valid values are: row, column")),
+        }
+    }
+}
+
+/// The shape of the grid and how images fill it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Grid {
+    pub rows: u32,
+    pub cols: u32,
+    pub fill_order: FillOrder,
+}
+
+impl Grid {
+    /// Determines the grid shape for `count` images.
+    ///
+    /// A missing dimension is derived from the other one, and a single row is
+    /// used if neither is given. When both are given they must hold every
+    /// image; how well the images fill them is checked separately.
+    pub fn resolve(rows: Option<u32>, cols: Option<u32>, count: usize, fill_order: FillOrder) -> Result<Self> {
+        let count = u32::try_from(count).context("Too many images")?;
+        let (rows, cols) = match (rows, cols) {
+            (Some(0), _) => bail!("Number of rows must be at least 1"),
+            (_, Some(0)) => bail!("Number of columns must be at least 1"),
+            (Some(rows), Some(cols)) => {
+                let Some(cells) = rows.checked_mul(cols) else {
+                    bail!("A grid of {rows} rows and {cols} columns is too large");
+                };
+                if cells < count {
+                    bail!("{count} images do not fit in {rows} rows and {cols} columns");
+                }
+                (rows, cols)
+            }
+            (Some(rows), None) => (rows, count.div_ceil(rows)),
+            (None, Some(cols)) => (count.div_ceil(cols), cols),
+            (None, None) => (1, count),
+        };
+        Ok(Self { rows, cols, fill_order })
+    }
+
+    /// Row and column of the `index`-th image.
+    pub fn position(self, index: u32) -> (u32, u32) {
+        match self.fill_order {
+            FillOrder::Row => (index / self.cols.max(1), index % self.cols.max(1)),
+            FillOrder::Column => (index % self.rows.max(1), index / self.rows.max(1)),
+        }
+    }
+
+    /// Index of the image placed at `row` and `col`.
+    pub fn index(self, row: u32, col: u32) -> usize {
+        let index = match self.fill_order {
+            FillOrder::Row => row * self.cols + col,
+            FillOrder::Column => col * self.rows + row,
+        };
+        index as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(rows: Option<u32>, cols: Option<u32>, count: usize) -> (u32, u32) {
+        let grid = Grid::resolve(rows, cols, count, FillOrder::Row).unwrap();
+        (grid.rows, grid.cols)
+    }
+
+    #[test]
+    fn derives_missing_dimensions() {
+        assert_eq!(shape(None, None, 5), (1, 5));
+        assert_eq!(shape(Some(2), None, 5), (2, 3));
+        assert_eq!(shape(None, Some(2), 5), (3, 2));
+        assert_eq!(shape(Some(2), Some(4), 5), (2, 4));
+    }
+
+    #[test]
+    fn rejects_invalid_shapes() {
+        assert!(Grid::resolve(Some(0), None, 4, FillOrder::Row).is_err());
+        assert!(Grid::resolve(None, Some(0), 4, FillOrder::Row).is_err());
+        assert!(Grid::resolve(Some(2), Some(2), 5, FillOrder::Row).is_err());
+        assert!(Grid::resolve(Some(70_000), Some(70_000), 1, FillOrder::Row).is_err());
+    }
+
+    #[test]
+    fn positions_follow_the_fill_order() {
+        let by_row = Grid::resolve(Some(2), Some(3), 6, FillOrder::Row).unwrap();
+        let by_column = Grid::resolve(Some(2), Some(3), 6, FillOrder::Column).unwrap();
+        assert_eq!(by_row.position(4), (1, 1));
+        assert_eq!(by_column.position(4), (0, 2));
+        for index in 0..6 {
+            let (row, col) = by_column.position(index);
+            assert_eq!(by_column.index(row, col), index as usize);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use crate::loader::is_empty_slot;
+use crate::natural::natural_cmp;
+use anyhow::{Context, Result, bail};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::UNIX_EPOCH;
+
+/// Extensions of the files picked up from directories and glob patterns.
+const IMAGE_EXTENSIONS: [&str; 9] = ["jpg", "jpeg", "png", "webp", "avif", "tif", "tiff", "bmp", "gif"];
+
+/// The order of the images found in a directory or by a glob pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortKey {
+    /// Natural order of the paths, so `2.png` comes before `10.png`
+    #[default]
+    Name,
+    /// Oldest modification time first
+    Mtime,
+    /// Smallest file first
+    Size,
+}
+
+impl FromStr for SortKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "name" => Ok(Self::Name),
+            "mtime" => Ok(Self::Mtime),
+            "size" => Ok(Self::Size),
+            _ => Err(format!("Invalid sort key: {s}. Valid values are: name, mtime, size")),
+        }
+    }
+}
+
+/// Reads a list of image paths from a file, or from stdin if `path` is `-`.
+/// Paths are separated by NUL bytes if there are any, such as in the output of
+/// `find -print0`, and by lines otherwise. Blank entries are ignored.
+pub fn read_list(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut bytes = Vec::new();
+    if path == Path::new("-") {
+        std::io::stdin()
+            .read_to_end(&mut bytes)
+            .context("Failed to read image list from stdin")?;
+    } else {
+        bytes = std::fs::read(path).with_context(|| format!("Failed to read image list {}", path.display()))?;
+    }
+
+    let separator = if bytes.contains(&0) { 0 } else { b'\n' };
+    bytes
+        .split(|&byte| byte == separator)
+        .map(|entry| entry.strip_suffix(b"\r").unwrap_or(entry))
+        .filter(|entry| !entry.trim_ascii().is_empty())
+        .map(path_from_bytes)
+        .collect()
+}
+
+/// Paths on Unix are arbitrary bytes, so they are taken as they are.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_wraps)] // Other platforms can fail here.
+fn path_from_bytes(bytes: &[u8]) -> Result<PathBuf> {
+    use std::os::unix::ffi::OsStrExt;
+    Ok(PathBuf::from(std::ffi::OsStr::from_bytes(bytes)))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> Result<PathBuf> {
+    let path = std::str::from_utf8(bytes).context("Image list is not valid UTF-8")?;
+    Ok(PathBuf::from(path))
+}
+
+/// Replaces every directory and glob pattern in `inputs` with the image files
+/// it contains, sorted by `sort`. Files and empty slot markers are kept in
+/// place, and so are paths that do not exist, for the missing image policy to
+/// handle.
+pub fn expand(inputs: Vec<PathBuf>, recursive: bool, sort: SortKey) -> Result<Vec<PathBuf>> {
+    let mut images = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let mut found = if input.is_dir() {
+            list_directory(&input, recursive)?
+        } else if !is_empty_slot(&input) && !input.exists() && is_glob(&input) {
+            expand_glob(&input)?
+        } else {
+            images.push(input);
+            continue;
+        };
+
+        if found.is_empty() {
+            bail!("No images found in {}", input.display());
+        }
+        sort_paths(&mut found, sort)?;
+        images.extend(found);
+    }
+    Ok(images)
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+fn is_glob(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '['])
+}
+
+fn list_directory(dir: &Path, recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut images = Vec::new();
+    collect_images(dir, recursive, &mut images)?;
+    Ok(images)
+}
+
+fn collect_images(dir: &Path, recursive: bool, images: &mut Vec<PathBuf>) -> Result<()> {
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read directory {}", dir.display()))?;
+    for entry in entries {
+        let entry = entry.with_context(|| format!("Failed to read directory {}", dir.display()))?;
+        let path = entry.path();
+        // Symlinked directories are not followed, so links cannot form a cycle.
+        if entry.file_type()?.is_dir() {
+            if recursive {
+                collect_images(&path, recursive, images)?;
+            }
+        } else if path.is_file() && is_image(&path) {
+            images.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn expand_glob(pattern: &Path) -> Result<Vec<PathBuf>> {
+    let pattern = pattern.to_string_lossy();
+    let paths = glob::glob(&pattern).with_context(|| format!("Invalid glob pattern: {pattern}"))?;
+    let mut images = Vec::new();
+    for path in paths {
+        let path = path.with_context(|| format!("Failed to expand glob pattern {pattern}"))?;
+        if path.is_file() && is_image(&path) {
+            images.push(path);
+        }
+    }
+    Ok(images)
+}
+
+/// Sorts by the chosen key, breaking ties in natural path order.
+fn sort_paths(paths: &mut Vec<PathBuf>, sort: SortKey) -> Result<()> {
+    let mut keyed = paths
+        .drain(..)
+        .map(|path| {
+            let key = match sort {
+                SortKey::Name => 0,
+                SortKey::Mtime | SortKey::Size => {
+                    let metadata = std::fs::metadata(&path)
+                        .with_context(|| format!("Failed to read metadata of {}", path.display()))?;
+                    if sort == SortKey::Size {
+                        u128::from(metadata.len())
+                    } else {
+                        metadata
+                            .modified()?
+                            .duration_since(UNIX_EPOCH)
+                            .unwrap_or_default()
+                            .as_nanos()
+                    }
+                }
+            };
+            Ok((key, path))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    keyed.sort_by(|(a_key, a), (b_key, b)| {
+        a_key
+            .cmp(b_key)
+            .then_with(|| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()))
+    });
+    paths.extend(keyed.into_iter().map(|(_, path)| path));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(contents: &[u8]) -> Vec<PathBuf> {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), contents).unwrap();
+        read_list(file.path()).unwrap()
+    }
+
+    #[test]
+    fn splits_lines_and_skips_blank_entries() {
+        assert_eq!(list(b"a.png\r\n\n  \nb c.png\n"), [PathBuf::from("a.png"), PathBuf::from("b c.png")]);
+    }
+
+    #[test]
+    fn prefers_nul_separators() {
+        assert_eq!(list(b"a\nb.png\0c.png\0"), [PathBuf::from("a\nb.png"), PathBuf::from("c.png")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_paths_that_are_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let path = PathBuf::from(std::ffi::OsStr::from_bytes(b"\xff.png"));
+        assert_eq!(list(b"\xff.png\n"), [path]);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::grid::Grid;
+use crate::loader::is_empty_slot;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// The part of an image path an automatic label is taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSegment {
+    /// File name without its extension
+    Stem,
+    /// File name including its extension
+    Name,
+    /// The n-th enclosing directory, 1 being the immediate parent
+    Parent(usize),
+}
+
+impl FromStr for PathSegment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "stem" => Ok(Self::Stem),
+            "name" => Ok(Self::Name),
+            "parent" => Ok(Self::Parent(1)),
+            other => match other.strip_prefix("parent:").map(str::parse::<usize>) {
+                Some(Ok(depth)) if depth > 0 => Ok(Self::Parent(depth)),
+                _ => Err(format!(
+                    "Invalid label source: {s}. Valid values are: stem, name, parent, parent:N"
+                )),
+            },
+        }
+    }
+}
+
+impl PathSegment {
+    /// Extracts this segment from `path`, or an empty string if it has none.
+    pub fn of(self, path: &Path) -> String {
+        let segment = match self {
+            Self::Stem => path.file_stem(),
+            Self::Name => path.file_name(),
+            Self::Parent(depth) => path.ancestors().nth(depth).and_then(Path::file_name),
+        };
+        segment.map(|s| s.to_string_lossy().into_owned()).unwrap_or_default()
+    }
+}
+
+/// Splits every label further at `delimiter`.
+pub fn split(labels: &[String], delimiter: &str) -> Vec<String> {
+    labels
+        .iter()
+        .flat_map(|label| label.split(delimiter))
+        .map(String::from)
+        .collect()
+}
+
+/// Reads labels or captions from a file, one per line, naming the file as
+/// `what` in errors. Blank lines give empty entries, and `\n` escapes within a
+/// line are kept for multiline labels.
+pub fn read_lines(path: &Path, what: &str) -> Result<Vec<String>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {what} file {}", path.display()))?;
+    Ok(text.lines().map(String::from).collect())
+}
+
+/// A header spanning several consecutive rows or columns, given as
+/// `LABEL:SPAN`. A label without a span covers a single row or column.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelGroup {
+    pub label: String,
+    pub span: u32,
+}
+
+impl FromStr for LabelGroup {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.rsplit_once(':').map(|(label, span)| (label, span.trim().parse::<u32>())) {
+            Some((_, Ok(0))) => Err(format!("Invalid label group: {s}. The span must be at least 1")),
+            Some((label, Ok(span))) => Ok(Self {
+                label: label.to_string(),
+                span,
+            }),
+            _ => Ok(Self {
+                label: s.to_string(),
+                span: 1,
+            }),
+        }
+    }
+}
+
+/// The first row or column covered by each group and how many it covers,
+/// paired with the group. Groups are cut off at the `count` rows or columns
+/// of the grid, so spans running past it never reach the layout.
+pub fn group_spans(groups: &[LabelGroup], count: u32) -> impl Iterator<Item = (u32, u32, &LabelGroup)> {
+    groups
+        .iter()
+        .scan(0u32, |start, group| {
+            let first = *start;
+            *start = start.saturating_add(group.span);
+            Some((first, group))
+        })
+        .take_while(move |&(first, _)| first < count)
+        .map(move |(first, group)| (first, group.span.min(count - first), group))
+}
+
+/// Labels each column after the topmost image in it.
+pub fn column_labels(images: &[PathBuf], grid: Grid, segment: PathSegment) -> Vec<String> {
+    (0..grid.cols)
+        .map(|col| label(images, (0..grid.rows).map(|row| grid.index(row, col)), segment))
+        .collect()
+}
+
+/// Labels each row after the leftmost image in it.
+pub fn row_labels(images: &[PathBuf], grid: Grid, segment: PathSegment) -> Vec<String> {
+    (0..grid.rows)
+        .map(|row| label(images, (0..grid.cols).map(|col| grid.index(row, col)), segment))
+        .collect()
+}
+
+/// Labels a row or column after its first image, skipping empty slots.
+fn label(images: &[PathBuf], indices: impl Iterator<Item = usize>, segment: PathSegment) -> String {
+    indices
+        .filter_map(|index| images.get(index))
+        .find(|path| !is_empty_slot(path))
+        .map(|path| segment.of(path))
+        .unwrap_or_default()
+}
@@ -0,0 +1,391 @@
+use crate::grid::{FillOrder, Grid};
+use anyhow::{Result, bail};
+use imageproc::rect::Rect;
+use std::str::FromStr;
+
+/// A rectangular region of the canvas in pixels.
+#[derive(Debug, Clone, Copy)]
+pub struct Area {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Area {
+    /// Top-left corner at which content of the given size is centered in this area.
+    pub fn centered(&self, width: u32, height: u32) -> (u32, u32) {
+        (
+            self.x + self.width.saturating_sub(width) / 2,
+            self.y + self.height.saturating_sub(height) / 2,
+        )
+    }
+
+    /// The area shrunk by `amount` on every side.
+    pub fn inset(self, amount: u32) -> Self {
+        let amount_x = amount.min(self.width / 2);
+        let amount_y = amount.min(self.height / 2);
+        Self {
+            x: self.x + amount_x,
+            y: self.y + amount_y,
+            width: self.width - 2 * amount_x,
+            height: self.height - 2 * amount_y,
+        }
+    }
+
+    /// The area as an imageproc rectangle, or `None` if it is empty.
+    pub fn rect(&self) -> Option<Rect> {
+        (self.width > 0 && self.height > 0)
+            .then(|| Rect::at(to_i32(self.x), to_i32(self.y)).of_size(self.width, self.height))
+    }
+}
+
+/// Size of a label band: measured from its labels, or a fixed number of pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Padding {
+    /// Fit the largest label exactly, plus the label inset on both sides
+    #[default]
+    Auto,
+    Fixed(u32),
+}
+
+impl FromStr for Padding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+        s.trim()
+            .parse()
+            .map(Self::Fixed)
+            .map_err(|_| format!("Invalid padding: {s}. Expected a number of pixels or auto"))
+    }
+}
+
+/// Space around the whole plot, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Margin {
+    pub top: u32,
+    pub right: u32,
+    pub bottom: u32,
+    pub left: u32,
+}
+
+impl Margin {
+    /// The same margin on every side.
+    pub fn uniform(pixels: u32) -> Self {
+        Self {
+            top: pixels,
+            right: pixels,
+            bottom: pixels,
+            left: pixels,
+        }
+    }
+}
+
+impl FromStr for Margin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!("Invalid margin: {s}. Expected one value for all sides or four as TOP,RIGHT,BOTTOM,LEFT")
+        };
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        match *values.as_slice() {
+            [all] => Ok(Self::uniform(all)),
+            [top, right, bottom, left] => Ok(Self {
+                top,
+                right,
+                bottom,
+                left,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Positions of the label bands, image cells and caption bands of a plot.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub rows: u32,
+    pub cols: u32,
+    pub fill_order: FillOrder,
+    pub cell_width: u32,
+    pub cell_height: u32,
+    pub top_padding: u32,
+    pub left_padding: u32,
+    /// Height of the band above the column labels for column groups
+    pub column_group_height: u32,
+    /// Width of the band left of the row labels for row groups
+    pub row_group_width: u32,
+    /// Height of the band above everything else for the title and subtitle
+    pub title_height: u32,
+    /// Height of the band below the grid for the footer
+    pub footer_height: u32,
+    /// Height of the caption band beneath each image, zero if there is none
+    pub caption_height: u32,
+    /// Horizontal space between neighboring columns
+    pub gap_x: u32,
+    /// Vertical space between neighboring rows
+    pub gap_y: u32,
+    pub margin: Margin,
+}
+
+impl Layout {
+    pub fn width(&self) -> u32 {
+        u32::try_from(self.extent().0).unwrap_or(u32::MAX)
+    }
+
+    pub fn height(&self) -> u32 {
+        u32::try_from(self.extent().1).unwrap_or(u32::MAX)
+    }
+
+    /// Width and height of the plot, computed without overflow so that
+    /// oversized layouts can still be measured and scaled down.
+    pub fn extent(&self) -> (u64, u64) {
+        let span = |count: u32, length: u64, gap: u32| {
+            u64::from(count)
+                .saturating_mul(length)
+                .saturating_add(u64::from(count.saturating_sub(1)) * u64::from(gap))
+        };
+        let sum = |lengths: &[u64]| lengths.iter().fold(0u64, |total, &length| total.saturating_add(length));
+        let width = sum(&[
+            self.margin.left.into(),
+            self.row_group_width.into(),
+            self.left_padding.into(),
+            span(self.cols, self.cell_width.into(), self.gap_x),
+            self.margin.right.into(),
+        ]);
+        let height = sum(&[
+            self.margin.top.into(),
+            self.title_height.into(),
+            self.column_group_height.into(),
+            self.top_padding.into(),
+            span(self.rows, u64::from(self.cell_height) + u64::from(self.caption_height), self.gap_y),
+            self.footer_height.into(),
+            self.margin.bottom.into(),
+        ]);
+        (width, height)
+    }
+
+    /// Fails if the plot is too large to be rendered.
+    pub fn check(&self) -> Result<()> {
+        let (width, height) = self.extent();
+        if width > u64::from(u32::MAX) || height > u64::from(u32::MAX) {
+            bail!("The plot would be {width}x{height} pixels, which is too large to render");
+        }
+        Ok(())
+    }
+
+    /// Top edge of the column headers, below the margin and the title band.
+    fn top(&self) -> u32 {
+        self.margin.top + self.title_height
+    }
+
+    /// Height of the column group and column label bands together.
+    fn header_height(&self) -> u32 {
+        self.column_group_height + self.top_padding
+    }
+
+    /// Width of the row group and row label bands together.
+    fn header_width(&self) -> u32 {
+        self.row_group_width + self.left_padding
+    }
+
+    fn row_height(&self) -> u32 {
+        self.cell_height + self.caption_height
+    }
+
+    /// Width of all columns and the gaps between them.
+    fn grid_width(&self) -> u32 {
+        self.cols * self.cell_width + self.cols.saturating_sub(1) * self.gap_x
+    }
+
+    /// Height of all rows and the gaps between them.
+    fn grid_height(&self) -> u32 {
+        self.rows * self.row_height() + self.rows.saturating_sub(1) * self.gap_y
+    }
+
+    fn column_x(&self, col: u32) -> u32 {
+        self.margin.left + self.header_width() + col * (self.cell_width + self.gap_x)
+    }
+
+    fn row_y(&self, row: u32) -> u32 {
+        self.top() + self.header_height() + row * (self.row_height() + self.gap_y)
+    }
+
+    pub fn cell(&self, row: u32, col: u32) -> Area {
+        Area {
+            x: self.column_x(col),
+            y: self.row_y(row),
+            width: self.cell_width,
+            height: self.cell_height,
+        }
+    }
+
+    /// The band beneath a cell reserved for its caption.
+    pub fn caption(&self, row: u32, col: u32) -> Area {
+        let cell = self.cell(row, col);
+        Area {
+            y: cell.y + cell.height,
+            height: self.caption_height,
+            ..cell
+        }
+    }
+
+    /// The band above a column reserved for its label.
+    pub fn column_label(&self, col: u32) -> Area {
+        Area {
+            x: self.column_x(col),
+            y: self.top() + self.column_group_height,
+            width: self.cell_width,
+            height: self.top_padding,
+        }
+    }
+
+    /// The band left of a row reserved for its label.
+    pub fn row_label(&self, row: u32) -> Area {
+        Area {
+            x: self.margin.left + self.row_group_width,
+            y: self.row_y(row),
+            width: self.left_padding,
+            height: self.cell_height,
+        }
+    }
+
+    /// The gaps between neighboring columns and between neighboring rows.
+    pub fn gutters(&self) -> impl Iterator<Item = Area> {
+        let columns = (1..self.cols).map(|col| Area {
+            x: self.column_x(col) - self.gap_x,
+            y: self.row_y(0),
+            width: self.gap_x,
+            height: self.grid_height(),
+        });
+        let rows = (1..self.rows).map(|row| Area {
+            x: self.column_x(0),
+            y: self.row_y(row) - self.gap_y,
+            width: self.grid_width(),
+            height: self.gap_y,
+        });
+        columns.chain(rows)
+    }
+
+    /// The band across the top of the plot reserved for the title and subtitle.
+    pub fn title(&self) -> Area {
+        Area {
+            x: self.margin.left,
+            y: self.margin.top,
+            width: self.header_width() + self.grid_width(),
+            height: self.title_height,
+        }
+    }
+
+    /// The band across the bottom of the plot reserved for the footer.
+    pub fn footer(&self) -> Area {
+        Area {
+            x: self.margin.left,
+            y: self.top() + self.header_height() + self.grid_height(),
+            width: self.header_width() + self.grid_width(),
+            height: self.footer_height,
+        }
+    }
+
+    /// The band above `span` columns starting at `col` reserved for their group label.
+    pub fn column_group(&self, col: u32, span: u32) -> Area {
+        Area {
+            x: self.column_x(col),
+            y: self.top(),
+            width: span * self.cell_width + span.saturating_sub(1) * self.gap_x,
+            height: self.column_group_height,
+        }
+    }
+
+    /// The band left of `span` rows starting at `row` reserved for their group label.
+    pub fn row_group(&self, row: u32, span: u32) -> Area {
+        let y = self.row_y(row);
+        Area {
+            x: self.margin.left,
+            y,
+            width: self.row_group_width,
+            height: (self.row_y(row + span.max(1) - 1) + self.cell_height).saturating_sub(y),
+        }
+    }
+
+    /// Lines of the given thickness centered in the gaps between neighboring
+    /// columns and between neighboring rows.
+    pub fn separators(&self, thickness: u32) -> impl Iterator<Item = Area> {
+        let columns = (1..self.cols).map(move |col| Area {
+            x: (self.column_x(col) - self.gap_x.div_ceil(2)).saturating_sub(thickness / 2),
+            y: self.row_y(0),
+            width: thickness,
+            height: self.grid_height(),
+        });
+        let rows = (1..self.rows).map(move |row| Area {
+            x: self.column_x(0),
+            y: (self.row_y(row) - self.gap_y.div_ceil(2)).saturating_sub(thickness / 2),
+            width: self.grid_width(),
+            height: thickness,
+        });
+        columns.chain(rows)
+    }
+
+    /// Lines of the given thickness along the inner edges of the label bands,
+    /// separating them from the images.
+    pub fn header_lines(&self, thickness: u32) -> Vec<Area> {
+        let mut lines = Vec::new();
+        if self.header_height() > 0 {
+            lines.push(Area {
+                x: self.margin.left,
+                y: (self.top() + self.header_height()).saturating_sub(thickness),
+                width: self.header_width() + self.grid_width(),
+                height: thickness,
+            });
+        }
+        if self.header_width() > 0 {
+            lines.push(Area {
+                x: (self.margin.left + self.header_width()).saturating_sub(thickness),
+                y: self.top(),
+                width: thickness,
+                height: self.header_height() + self.grid_height(),
+            });
+        }
+        lines
+    }
+
+    /// The same layout with cells of a different size.
+    pub fn with_cell_size(&self, width: u32, height: u32) -> Self {
+        Self {
+            cell_width: width,
+            cell_height: height,
+            ..self.clone()
+        }
+    }
+
+    /// Shrinks the image cells by `factor`, keeping at least one pixel.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn scale_cells(&mut self, factor: f64) {
+        let scale = |length: u32| ((f64::from(length) * factor).floor() as u32).max(1);
+        self.cell_width = scale(self.cell_width);
+        self.cell_height = scale(self.cell_height);
+    }
+
+    /// Every cell position in the order images fill the grid.
+    pub fn positions(&self) -> impl Iterator<Item = (u32, u32)> {
+        let grid = Grid {
+            rows: self.rows,
+            cols: self.cols,
+            fill_order: self.fill_order,
+        };
+        (0..self.rows * self.cols).map(move |index| grid.position(index))
+    }
+}
+
+/// Converts a pixel coordinate for imageproc, which uses signed coordinates.
+pub fn to_i32(value: u32) -> i32 {
+    i32::try_from(value).unwrap_or(i32::MAX)
+}
@@ -0,0 +1,140 @@
+use crate::layout::Layout;
+use anyhow::{Result, bail};
+
+/// Upper bounds on the size of the rendered plot.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutputLimits {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub pixels: Option<u64>,
+}
+
+impl OutputLimits {
+    /// The largest factor, at most 1, by which the image cells of `layout`
+    /// can be scaled so the plot fits every limit. Label bands and other
+    /// fixed areas keep their size so text stays legible.
+    pub fn scale(&self, layout: &Layout) -> Result<f64> {
+        #[allow(clippy::cast_precision_loss)]
+        let to_f64 = |(width, height): (u64, u64)| (width as f64, height as f64);
+        let (fixed_width, fixed_height) = to_f64(layout.with_cell_size(0, 0).extent());
+        let (width, height) = to_f64(layout.extent());
+        let cells_width = width - fixed_width;
+        let cells_height = height - fixed_height;
+
+        let mut scale: f64 = 1.0;
+        if let Some(max_width) = self.width {
+            scale = scale.min(fit_linear(f64::from(max_width), fixed_width, cells_width, "width")?);
+        }
+        if let Some(max_height) = self.height {
+            scale = scale.min(fit_linear(f64::from(max_height), fixed_height, cells_height, "height")?);
+        }
+        if let Some(max_pixels) = self.pixels {
+            #[allow(clippy::cast_precision_loss)]
+            let max_pixels = max_pixels as f64;
+            if fixed_width * fixed_height >= max_pixels {
+                bail!("Labels and padding alone exceed --max-pixels {max_pixels}");
+            }
+            // Solve (fixed_width + s * cells_width) * (fixed_height + s * cells_height) = max_pixels for s.
+            let a = cells_width * cells_height;
+            let b = fixed_width * cells_height + fixed_height * cells_width;
+            let c = fixed_width * fixed_height - max_pixels;
+            let pixel_scale = if a > 0.0 {
+                (-b + (b * b - 4.0 * a * c).sqrt()) / (2.0 * a)
+            } else if b > 0.0 {
+                -c / b
+            } else {
+                1.0
+            };
+            scale = scale.min(pixel_scale);
+        }
+        Ok(scale)
+    }
+
+    /// Fails if `layout` exceeds any limit.
+    pub fn check(&self, layout: &Layout) -> Result<()> {
+        let (width, height) = layout.extent();
+        if let Some(max_width) = self.width.filter(|&max| width > u64::from(max)) {
+            bail!("The plot is {width} pixels wide even with scaled images, more than --max-width {max_width}");
+        }
+        if let Some(max_height) = self.height.filter(|&max| height > u64::from(max)) {
+            bail!("The plot is {height} pixels high even with scaled images, more than --max-height {max_height}");
+        }
+        if let Some(max_pixels) = self.pixels.filter(|&max| width.saturating_mul(height) > max) {
+            bail!(
+                "The plot has {} pixels even with scaled images, more than --max-pixels {max_pixels}",
+                width.saturating_mul(height)
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Scale at which `fixed + scale * cells` equals `max` along one axis.
+fn fit_linear(max: f64, fixed: f64, cells: f64, axis: &str) -> Result<f64> {
+    if fixed >= max {
+        bail!("Labels and padding alone exceed --max-{axis} {max}");
+    }
+    Ok(if cells > 0.0 { (max - fixed) / cells } else { 1.0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::FillOrder;
+    use crate::layout::Margin;
+
+    /// Two 100x100 cells side by side below a 20 pixel label band, 200x120 in total.
+    fn layout() -> Layout {
+        Layout {
+            rows: 1,
+            cols: 2,
+            fill_order: FillOrder::Row,
+            cell_width: 100,
+            cell_height: 100,
+            top_padding: 20,
+            left_padding: 0,
+            column_group_height: 0,
+            row_group_width: 0,
+            title_height: 0,
+            footer_height: 0,
+            caption_height: 0,
+            gap_x: 0,
+            gap_y: 0,
+            margin: Margin::default(),
+        }
+    }
+
+    fn limits(width: Option<u32>, height: Option<u32>, pixels: Option<u64>) -> OutputLimits {
+        OutputLimits { width, height, pixels }
+    }
+
+    #[test]
+    fn keeps_layouts_within_the_limits() {
+        assert!((limits(None, None, None).scale(&layout()).unwrap() - 1.0).abs() < 1e-9);
+        assert!((limits(Some(400), Some(400), Some(1_000_000)).scale(&layout()).unwrap() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn scales_cells_but_not_labels() {
+        assert!((limits(Some(100), None, None).scale(&layout()).unwrap() - 0.5).abs() < 1e-9);
+        // Only the 100 pixels of cell height shrink, the label band stays 20 pixels.
+        assert!((limits(None, Some(70), None).scale(&layout()).unwrap() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn solves_the_pixel_limit_exactly() {
+        let scale = limits(None, None, Some(7_000)).scale(&layout()).unwrap();
+        // (200 s) * (20 + 100 s) = 7000 at s = 0.5
+        assert!((scale - 0.5).abs() < 1e-9);
+        let mut scaled = layout();
+        scaled.scale_cells(scale);
+        assert!(limits(None, None, Some(7_000)).check(&scaled).is_ok());
+        assert!(limits(None, None, Some(6_999)).check(&scaled).is_err());
+    }
+
+    #[test]
+    fn rejects_limits_below_the_fixed_size() {
+        assert!(limits(None, Some(20), None).scale(&layout()).is_err());
+        assert!(limits(None, None, Some(0)).scale(&layout()).is_err());
+    }
+}
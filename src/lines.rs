@@ -0,0 +1,95 @@
+use crate::layout::Area;
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::draw_filled_rect_mut;
+use std::str::FromStr;
+
+/// How grid lines and cell borders are stroked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineStyle {
+    #[default]
+    Solid,
+    /// Dashes four times as long as the line is thick, with equal gaps
+    Dashed,
+}
+
+impl FromStr for LineStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "solid" => Ok(Self::Solid),
+            "dashed" => Ok(Self::Dashed),
+            _ => Err(format!("Invalid line style: {s}. Valid values are: solid, dashed")),
+        }
+    }
+}
+
+/// Strokes a straight line covering `area`. Dashes run along its longer side.
+pub fn draw(canvas: &mut RgbaImage, area: Area, style: LineStyle, color: Rgba<u8>) {
+    match style {
+        LineStyle::Solid => fill(canvas, area, color),
+        LineStyle::Dashed => {
+            let horizontal = area.width >= area.height;
+            let (length, thickness) = if horizontal {
+                (area.width, area.height)
+            } else {
+                (area.height, area.width)
+            };
+            let dash = (thickness * 4).max(4);
+            for start in (0..length).step_by(2 * dash as usize) {
+                let size = dash.min(length - start);
+                let segment = if horizontal {
+                    Area {
+                        x: area.x + start,
+                        width: size,
+                        ..area
+                    }
+                } else {
+                    Area {
+                        y: area.y + start,
+                        height: size,
+                        ..area
+                    }
+                };
+                fill(canvas, segment, color);
+            }
+        }
+    }
+}
+
+/// Strokes a border of the given thickness just inside `area`.
+pub fn draw_border(canvas: &mut RgbaImage, area: Area, thickness: u32, style: LineStyle, color: Rgba<u8>) {
+    if area.width == 0 || area.height == 0 {
+        return;
+    }
+    let thickness = thickness.min(area.width / 2).min(area.height / 2).max(1);
+    let sides = [
+        Area {
+            height: thickness,
+            ..area
+        },
+        Area {
+            y: area.y + area.height - thickness,
+            height: thickness,
+            ..area
+        },
+        Area {
+            width: thickness,
+            ..area
+        },
+        Area {
+            x: area.x + area.width - thickness,
+            width: thickness,
+            ..area
+        },
+    ];
+    for side in sides {
+        draw(canvas, side, style, color);
+    }
+}
+
+fn fill(canvas: &mut RgbaImage, area: Area, color: Rgba<u8>) {
+    if let Some(rect) = area.rect() {
+        draw_filled_rect_mut(canvas, rect, color);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::fit::{self, Fit};
+use anyhow::{Context, Result};
+use image::RgbaImage;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Image list entries that reserve an empty cell instead of naming a file.
+const EMPTY_SLOT_MARKERS: [&str; 2] = ["_", "-"];
+
+/// An image list entry that reserves an empty cell.
+pub fn empty_slot() -> PathBuf {
+    PathBuf::from(EMPTY_SLOT_MARKERS[0])
+}
+
+pub fn is_empty_slot(path: &Path) -> bool {
+    path.to_str().is_some_and(|path| EMPTY_SLOT_MARKERS.contains(&path))
+}
+
+/// What occupies one slot of the grid.
+pub enum Cell {
+    Image(RgbaImage),
+    /// An input that failed to load, drawn as a placeholder
+    Missing(PathBuf),
+    /// A slot deliberately left blank with `_` or `-`
+    Empty,
+}
+
+impl Cell {
+    pub fn image(&self) -> Option<&RgbaImage> {
+        match self {
+            Self::Image(image) => Some(image),
+            Self::Missing(_) | Self::Empty => None,
+        }
+    }
+}
+
+/// How inputs that are missing or cannot be decoded are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingPolicy {
+    /// Leave the input out; later images move up to fill its place
+    Skip,
+    /// Keep the cell and mark it as missing
+    Placeholder,
+    /// Fail the plot
+    #[default]
+    Error,
+}
+
+impl FromStr for MissingPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(Self::Skip),
+            "placeholder" => Ok(Self::Placeholder),
+            "error" => Ok(Self::Error),
+            _ => Err(format!(
+                "Invalid missing image policy: {s}. Valid values are: skip, placeholder, error"
+            )),
+        }
+    }
+}
+
+/// Decodes images on the blocking thread pool and normalizes them to 8-bit
+/// RGBA so alpha survives compositing. Every input gets its own result so
+/// one bad file does not hide the others; empty slot markers become empty
+/// cells.
+pub async fn load_images(paths: &[PathBuf], workers: &Arc<Semaphore>) -> Result<Vec<Result<Cell>>> {
+    map_blocking(paths.to_vec(), workers, |path: PathBuf| {
+        if is_empty_slot(&path) {
+            return Ok(Ok(Cell::Empty));
+        }
+        let image = image::open(&path)
+            .with_context(|| format!("Failed to open image {}", path.display()));
+        Ok(image.map(|image| Cell::Image(image.into_rgba8())))
+    })
+    .await
+}
+
+/// Fits every image into a uniform cell on the blocking thread pool.
+pub async fn fit_images(cells: Vec<Cell>, workers: &Arc<Semaphore>, fit: Fit) -> Result<Vec<Cell>> {
+    map_blocking(cells, workers, move |cell| {
+        Ok(match cell {
+            Cell::Image(image) => Cell::Image(fit::fit_image(image, fit)),
+            other => other,
+        })
+    })
+    .await
+}
+
+/// Applies `f` to every input on the blocking thread pool. At most one input
+/// per available `workers` permit is processed at a time; the result is in
+/// input order regardless of which task finishes first.
+async fn map_blocking<I, T, F>(inputs: Vec<I>, workers: &Arc<Semaphore>, f: F) -> Result<Vec<T>>
+where
+    I: Send + 'static,
+    T: Send + 'static,
+    F: Fn(I) -> Result<T> + Send + Sync + 'static,
+{
+    let f = Arc::new(f);
+    let count = inputs.len();
+    let mut tasks = JoinSet::new();
+
+    for (index, input) in inputs.into_iter().enumerate() {
+        let permit = Arc::clone(workers).acquire_owned().await?;
+        let f = Arc::clone(&f);
+        tasks.spawn_blocking(move || {
+            let _permit = permit;
+            f(input).map(|output| (index, output))
+        });
+    }
+
+    let mut outputs: Vec<Option<T>> = (0..count).map(|_| None).collect();
+    while let Some(result) = tasks.join_next().await {
+        let (index, output) = result??;
+        outputs[index] = Some(output);
+    }
+
+    Ok(outputs.into_iter().flatten().collect())
+}
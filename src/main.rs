@@ -1,90 +1,552 @@
 #![warn(clippy::all, clippy::pedantic)]
 
 use anyhow::Result;
-use clap::Parser;
-use imx::xyplot::{PlotConfig, LabelAlignment, DEFAULT_TOP_PADDING, DEFAULT_LEFT_PADDING};
+use captions::CaptionPosition;
+use clap::{CommandFactory, FromArgMatches, Parser};
+use fit::{CellSize, FitMode, Resample};
+use grid::{FillOrder, Grid};
+use inputs::SortKey;
+use labels::{LabelGroup, PathSegment};
+use loader::MissingPolicy;
+use layout::{Margin, Padding};
+use lines::LineStyle;
+use limits::OutputLimits;
+use output::{Encoding, OutputFormat, PngCompression};
+use plot::{Heading, PlotConfig};
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
-use std::str::FromStr;
+use std::sync::Arc;
+use text::{CAPTION_FONT_SIZE, LabelAlignment, FOOTER_FONT_SIZE, LABEL_FONT_SIZE, SUBTITLE_FONT_SIZE, TITLE_FONT_SIZE};
+use theme::{Color, ThemeName};
+use tokio::sync::Semaphore;
 
-/// Wrapper type for LabelAlignment to implement FromStr
-#[derive(Debug, Clone, Copy)]
-struct AlignmentArg(LabelAlignment);
+mod batch;
+mod captions;
+mod config;
+mod fit;
+mod grid;
+mod inputs;
+mod labels;
+mod layout;
+mod limits;
+mod lines;
+mod loader;
+mod natural;
+mod output;
+mod plot;
+mod sweep;
+mod text;
+mod theme;
+mod validate;
 
-impl FromStr for AlignmentArg {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "start" => Ok(Self(LabelAlignment::Start)),
-            "center" => Ok(Self(LabelAlignment::Center)),
-            "end" => Ok(Self(LabelAlignment::End)),
-            _ => Err(format!("Invalid alignment: {s}. Valid values are: start, center, end")),
-        }
-    }
-}
-
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
+#[allow(clippy::struct_excessive_bools)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// List of image file paths
-    #[arg(required = true, trailing_var_arg = true)]
+    /// List of image file paths, directories and quoted glob patterns such as "outputs/*.png".
+    /// Flags taking several values consume the arguments after them, so give images first
+    /// or separate them with --.
+    #[arg(required_unless_present_any = ["images_from", "sweep_dir", "config", "batch"])]
     images: Vec<PathBuf>,
 
+    /// Read more image paths from a file, or from stdin if "-", one per line or
+    /// NUL-separated. They are added after the images given as arguments.
+    #[arg(long)]
+    images_from: Option<PathBuf>,
+
+    /// Include images in subdirectories of directories given as inputs
+    #[arg(long)]
+    recursive: bool,
+
+    /// Order of the images found in a directory or by a glob pattern (name, mtime, size)
+    #[arg(long, default_value = "name")]
+    sort: SortKey,
+
+    /// Load the plot description from a TOML, YAML or JSON file. Flags given on the
+    /// command line override values from the file.
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Render every plot listed in a TOML, YAML or JSON manifest concurrently. Flags given
+    /// on the command line apply to every plot.
+    #[arg(long, conflicts_with_all = ["images", "images_from", "config", "sweep_dir"])]
+    batch: Option<PathBuf>,
+
+    /// Build the grid from the files in this directory instead of an explicit image list
+    #[arg(
+        long,
+        requires = "pattern",
+        conflicts_with_all = ["images", "images_from", "rows", "cols", "fill_order"]
+    )]
+    sweep_dir: Option<PathBuf>,
+
+    /// File name pattern for --sweep-dir, either a template such as "seed_{x}_cfg_{y}.png"
+    /// or a regex with named x and y groups. Columns follow x and rows follow y.
+    #[arg(long, requires = "sweep_dir")]
+    pattern: Option<String>,
+
     /// Output file name for the generated plot
     #[arg(long, default_value = "output.jpg")]
     output: PathBuf,
 
-    /// Number of rows to display the images
-    #[arg(long, default_value_t = 1)]
-    rows: u32,
+    /// Output format (jpeg, png, webp, avif, tiff), inferred from the --output extension by default
+    #[arg(long)]
+    format: Option<OutputFormat>,
+
+    /// Encoder quality from 1 to 100 for lossy formats (jpeg, avif)
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=100))]
+    quality: Option<u8>,
 
-    /// List of labels for each row. Provide multiple labels after a single --row-labels flag.
+    /// PNG compression level (fast, default, best)
+    #[arg(long, default_value = "default")]
+    png_compression: PngCompression,
+
+    /// Number of rows to display the images (derived from --cols, or 1 if neither is given)
+    #[arg(long)]
+    rows: Option<u32>,
+
+    /// Number of columns to display the images (derived from --rows if not given)
+    #[arg(long)]
+    cols: Option<u32>,
+
+    /// Order in which images fill the grid (row, column)
+    #[arg(long, default_value = "row")]
+    fill_order: FillOrder,
+
+    /// List of labels for each row. Provide multiple labels after a single --row-labels flag;
+    /// quoted labels may contain spaces.
     /// Example: --row-labels "Row 1" "Row 2" "Row 3"
-    #[arg(long, num_args = 1.., value_delimiter = ' ')]
+    #[arg(long, num_args = 1.., conflicts_with = "row_labels_file")]
     row_labels: Vec<String>,
 
-    /// List of labels for each column. Provide multiple labels after a single --column-labels flag.
+    /// List of labels for each column. Provide multiple labels after a single --column-labels flag;
+    /// quoted labels may contain spaces.
     /// Example: --column-labels "Col 1" "Col 2" "Col 3"
-    #[arg(long, num_args = 1.., value_delimiter = ' ')]
+    #[arg(long, num_args = 1.., conflicts_with = "column_labels_file")]
     column_labels: Vec<String>,
 
+    /// Read row labels from a file, one per line (\n within a line starts a new line of the label)
+    #[arg(long)]
+    row_labels_file: Option<PathBuf>,
+
+    /// Read column labels from a file, one per line (\n within a line starts a new line of the label)
+    #[arg(long)]
+    column_labels_file: Option<PathBuf>,
+
+    /// Also split every --row-labels and --column-labels value at this delimiter, e.g. "|"
+    #[arg(long, value_parser = clap::builder::NonEmptyStringValueParser::new())]
+    label_delimiter: Option<String>,
+
+    /// Headers spanning several columns, drawn above the column labels, as LABEL:SPAN.
+    /// Example: --column-groups "Model A:2" "Model B:2"
+    #[arg(long, num_args = 1..)]
+    column_groups: Vec<LabelGroup>,
+
+    /// Headers spanning several rows, drawn left of the row labels, as LABEL:SPAN
+    #[arg(long, num_args = 1..)]
+    row_groups: Vec<LabelGroup>,
+
+    /// Derive row and column labels that were not given from the image paths
+    #[arg(long)]
+    auto_labels: bool,
+
+    /// Path segment that automatic column labels are taken from (stem, name, parent, parent:N)
+    #[arg(long, default_value = "stem")]
+    column_label_source: PathSegment,
+
+    /// Path segment that automatic row labels are taken from (stem, name, parent, parent:N)
+    #[arg(long, default_value = "parent")]
+    row_label_source: PathSegment,
+
     /// Alignment of column labels (start, center, end)
     #[arg(long, default_value = "center")]
-    column_label_alignment: AlignmentArg,
+    column_label_alignment: LabelAlignment,
 
     /// Alignment of row labels (start, center, end)
     #[arg(long, default_value = "center")]
-    row_label_alignment: AlignmentArg,
+    row_label_alignment: LabelAlignment,
 
     /// Enable debug mode to visualize layout
     #[arg(long)]
     debug: bool,
 
-    /// Space reserved at the top of the plot for labels and padding
-    #[arg(long, default_value_t = DEFAULT_TOP_PADDING)]
-    top_padding: u32,
+    /// Height of the column label band in pixels, or auto to fit the labels
+    #[arg(long, default_value = "auto")]
+    top_padding: Padding,
+
+    /// Width of the row label band in pixels, or auto to fit the labels
+    #[arg(long, default_value = "auto")]
+    left_padding: Padding,
+
+    /// Height of the column group band in pixels, or auto to fit the groups
+    #[arg(long, default_value = "auto")]
+    column_group_padding: Padding,
+
+    /// Width of the row group band in pixels, or auto to fit the groups
+    #[arg(long, default_value = "auto")]
+    row_group_padding: Padding,
+
+    /// Space between labels and the edges of their bands in pixels
+    #[arg(long, default_value_t = 8)]
+    label_inset: u32,
+
+    /// Horizontal gap between columns in pixels
+    #[arg(long, default_value_t = 0)]
+    gap_x: u32,
+
+    /// Vertical gap between rows in pixels
+    #[arg(long, default_value_t = 0)]
+    gap_y: u32,
+
+    /// Space around the plot in pixels, either one value or TOP,RIGHT,BOTTOM,LEFT
+    #[arg(long, default_value = "0")]
+    margin: Margin,
+
+    /// Thickness of a border drawn around each image cell (0 for none)
+    #[arg(long, default_value_t = 0)]
+    cell_border: u32,
+
+    /// Thickness of separator lines between rows and columns (0 for none)
+    #[arg(long, default_value_t = 0)]
+    grid_lines: u32,
+
+    /// Thickness of the lines between the label areas and the images (0 for none)
+    #[arg(long, default_value_t = 0)]
+    header_line: u32,
+
+    /// Style of cell borders and grid lines (solid, dashed)
+    #[arg(long, default_value = "solid")]
+    line_style: LineStyle,
+
+    /// Caption for each image, in image order. Provide multiple captions after a single --captions flag.
+    /// Example: --captions "seed 1" "seed 2"
+    #[arg(long, num_args = 1.., conflicts_with_all = ["captions_file", "caption_template"])]
+    captions: Vec<String>,
+
+    /// Read captions from a file, one per line in image order
+    #[arg(long, conflicts_with = "caption_template")]
+    captions_file: Option<PathBuf>,
+
+    /// Caption every image from a template using {filename}, {stem}, {path} and {index}
+    /// Example: --caption-template "#{index}: {stem}"
+    #[arg(long)]
+    caption_template: Option<String>,
+
+    /// Alignment of captions (start, center, end)
+    #[arg(long, default_value = "center")]
+    caption_alignment: LabelAlignment,
+
+    /// Where captions are drawn (below, overlay)
+    #[arg(long, default_value = "below")]
+    caption_position: CaptionPosition,
+
+    /// Title drawn above the plot (supports multiline text with \n)
+    #[arg(long)]
+    title: Option<String>,
+
+    /// Subtitle drawn beneath the title
+    #[arg(long)]
+    subtitle: Option<String>,
+
+    /// Footer drawn below the plot
+    #[arg(long)]
+    footer: Option<String>,
+
+    /// Alignment of the title (start, center, end)
+    #[arg(long, default_value = "center")]
+    title_alignment: LabelAlignment,
+
+    /// Alignment of the subtitle (start, center, end)
+    #[arg(long, default_value = "center")]
+    subtitle_alignment: LabelAlignment,
+
+    /// Alignment of the footer (start, center, end)
+    #[arg(long, default_value = "center")]
+    footer_alignment: LabelAlignment,
+
+    /// Font file for all text (defaults to the built-in `DejaVu Sans`)
+    #[arg(long)]
+    font: Option<PathBuf>,
+
+    /// Font files tried in order for characters the primary font lacks, e.g. CJK or emoji fonts
+    #[arg(long, num_args = 1..)]
+    fallback_font: Vec<PathBuf>,
 
-    /// Space reserved at the left of the plot for labels and padding
-    #[arg(long, default_value_t = DEFAULT_LEFT_PADDING)]
-    left_padding: u32,
+    /// Pixel height of row and column label text
+    #[arg(long, default_value_t = LABEL_FONT_SIZE)]
+    font_size: f32,
+
+    /// Pixel height of column label text (defaults to --font-size)
+    #[arg(long)]
+    column_label_font_size: Option<f32>,
+
+    /// Pixel height of row label text (defaults to --font-size)
+    #[arg(long)]
+    row_label_font_size: Option<f32>,
+
+    /// Pixel height of caption text
+    #[arg(long, default_value_t = CAPTION_FONT_SIZE)]
+    caption_font_size: f32,
+
+    /// Pixel height of the title
+    #[arg(long, default_value_t = TITLE_FONT_SIZE)]
+    title_font_size: f32,
+
+    /// Pixel height of the subtitle
+    #[arg(long, default_value_t = SUBTITLE_FONT_SIZE)]
+    subtitle_font_size: f32,
+
+    /// Pixel height of the footer
+    #[arg(long, default_value_t = FOOTER_FONT_SIZE)]
+    footer_font_size: f32,
+
+    /// Built-in color theme (light, dark)
+    #[arg(long, default_value = "light")]
+    theme: ThemeName,
+
+    /// Background color as hex (#rrggbb, #rrggbbaa), `rgb()`, `rgba()` or a name (overrides the theme)
+    #[arg(long)]
+    background: Option<Color>,
+
+    /// Text color for labels and captions (overrides the theme)
+    #[arg(long)]
+    text_color: Option<Color>,
+
+    /// Color of grid lines and layout debugging borders (overrides the theme)
+    #[arg(long)]
+    grid_line_color: Option<Color>,
+
+    /// Normalize every image into a cell of this size, e.g. 512x512 (defaults to the largest image)
+    #[arg(long)]
+    cell_size: Option<CellSize>,
+
+    /// How images are fitted into their cells (contain, cover, stretch, none)
+    #[arg(long, default_value = "none")]
+    fit: FitMode,
+
+    /// Maximum output width in pixels; image cells are scaled down to fit
+    #[arg(long)]
+    max_width: Option<u32>,
+
+    /// Maximum output height in pixels; image cells are scaled down to fit
+    #[arg(long)]
+    max_height: Option<u32>,
+
+    /// Maximum number of output pixels; image cells are scaled down to fit
+    #[arg(long)]
+    max_pixels: Option<u64>,
+
+    /// Filter used when scaling images (nearest, triangle, catmull-rom, gaussian, lanczos3)
+    #[arg(long, default_value = "lanczos3")]
+    resample: Resample,
+
+    /// Make the background fully transparent and keep image alpha (requires PNG, WebP,
+    /// TIFF or AVIF output)
+    #[arg(long)]
+    transparent: bool,
+
+    /// What to do with images that are missing or cannot be decoded (skip, placeholder, error)
+    #[arg(long, default_value = "error")]
+    missing: MissingPolicy,
+
+    /// Cut off wrapped row and column labels after this many lines with an ellipsis
+    #[arg(long)]
+    max_label_lines: Option<NonZeroUsize>,
+
+    /// Warn about label counts that do not match the grid, empty rows or columns and a
+    /// ragged last row instead of failing
+    #[arg(long)]
+    lenient: bool,
+
+    /// Maximum number of images decoded at once (defaults to the number of CPUs)
+    #[arg(long)]
+    jobs: Option<NonZeroUsize>,
+}
+
+impl Args {
+    /// Splits labels at the delimiter and reads label files, which replace any
+    /// labels given inline.
+    fn resolve_labels(&mut self) -> Result<()> {
+        if let Some(delimiter) = &self.label_delimiter {
+            if delimiter.is_empty() {
+                anyhow::bail!("Label delimiter must not be empty");
+            }
+            self.row_labels = labels::split(&self.row_labels, delimiter);
+            self.column_labels = labels::split(&self.column_labels, delimiter);
+        }
+        if let Some(path) = &self.row_labels_file {
+            self.row_labels = labels::read_lines(path, "row labels")?;
+        }
+        if let Some(path) = &self.column_labels_file {
+            self.column_labels = labels::read_lines(path, "column labels")?;
+        }
+        Ok(())
+    }
+
+    /// Builds the image list from a sweep, the positional arguments and an image
+    /// list file, expanding directories and glob patterns.
+    fn resolve_inputs(&mut self) -> Result<()> {
+        if let (Some(dir), Some(pattern)) = (&self.sweep_dir, &self.pattern) {
+            // A config file can still combine these with a sweep, which clap cannot see.
+            if self.rows.is_some() || self.cols.is_some() || self.fill_order != FillOrder::Row {
+                anyhow::bail!("--rows, --cols and --fill-order cannot be used with --sweep-dir, which arranges the grid itself");
+            }
+            let grid = sweep::discover(dir, &sweep::compile_pattern(pattern)?)?;
+            self.images = grid.images;
+            // Sweep images are already arranged row by row.
+            self.rows = Some(grid.rows);
+            self.cols = None;
+            self.fill_order = FillOrder::Row;
+            if self.row_labels.is_empty() {
+                self.row_labels = grid.row_labels;
+            }
+            if self.column_labels.is_empty() {
+                self.column_labels = grid.column_labels;
+            }
+        }
+
+        if let Some(path) = &self.images_from {
+            self.images.extend(inputs::read_list(path)?);
+        }
+        self.images = inputs::expand(std::mem::take(&mut self.images), self.recursive, self.sort)?;
+
+        if self.images.is_empty() {
+            anyhow::bail!("No images provided");
+        }
+        Ok(())
+    }
+
+    /// Resolves the image list and labels into the configuration passed to the plotter.
+    fn into_plot_config(mut self) -> Result<PlotConfig> {
+        let encoding = Encoding::resolve(
+            &self.output,
+            self.format,
+            self.quality,
+            self.png_compression,
+            self.transparent,
+        )?;
+        self.resolve_labels()?;
+        self.resolve_inputs()?;
+
+        let grid = Grid::resolve(self.rows, self.cols, self.images.len(), self.fill_order)?;
+
+        if self.auto_labels {
+            if self.column_labels.is_empty() {
+                self.column_labels = labels::column_labels(&self.images, grid, self.column_label_source);
+            }
+            if self.row_labels.is_empty() {
+                self.row_labels = labels::row_labels(&self.images, grid, self.row_label_source);
+            }
+        }
+
+        // Skipping moves later images up, so they would no longer line up with
+        // labels given for their original row and column.
+        let labeled = !(self.row_labels.is_empty()
+            && self.column_labels.is_empty()
+            && self.row_groups.is_empty()
+            && self.column_groups.is_empty());
+        if self.missing == MissingPolicy::Skip && labeled {
+            anyhow::bail!(
+                "--missing skip cannot be used with row or column labels, since later images would move \
+                 under the wrong labels. Use --missing placeholder to keep their positions"
+            );
+        }
+
+        validate::report(&validate::check(&self, grid), self.lenient)?;
+
+        let captions = if let Some(template) = &self.caption_template {
+            captions::from_template(template, &self.images)
+        } else if let Some(path) = &self.captions_file {
+            // Captions are listed one per line in image order, like labels.
+            labels::read_lines(path, "captions")?
+        } else {
+            self.captions
+        };
+
+        let mut theme = self.theme.theme();
+        theme.background = self.background.unwrap_or(theme.background);
+        theme.text = self.text_color.unwrap_or(theme.text);
+        theme.grid_line = self.grid_line_color.unwrap_or(theme.grid_line);
+        if self.transparent {
+            theme.background = theme.background.with_alpha(0);
+        }
+
+        Ok(PlotConfig {
+            images: self.images,
+            output: self.output,
+            grid,
+            row_labels: self.row_labels,
+            column_labels: self.column_labels,
+            column_label_alignment: self.column_label_alignment,
+            row_label_alignment: self.row_label_alignment,
+            debug_mode: self.debug,
+            top_padding: self.top_padding,
+            left_padding: self.left_padding,
+            column_groups: self.column_groups,
+            row_groups: self.row_groups,
+            column_group_padding: self.column_group_padding,
+            row_group_padding: self.row_group_padding,
+            label_inset: self.label_inset,
+            title: heading(self.title, self.title_font_size, self.title_alignment),
+            subtitle: heading(self.subtitle, self.subtitle_font_size, self.subtitle_alignment),
+            footer: heading(self.footer, self.footer_font_size, self.footer_alignment),
+            captions,
+            caption_alignment: self.caption_alignment,
+            caption_position: self.caption_position,
+            font: self.font,
+            fallback_fonts: self.fallback_font,
+            column_label_font_size: self.column_label_font_size.unwrap_or(self.font_size),
+            row_label_font_size: self.row_label_font_size.unwrap_or(self.font_size),
+            caption_font_size: self.caption_font_size,
+            theme,
+            encoding,
+            cell_size: self.cell_size,
+            fit: self.fit,
+            resample: self.resample,
+            limits: OutputLimits {
+                width: self.max_width,
+                height: self.max_height,
+                pixels: self.max_pixels,
+            },
+            gap_x: self.gap_x,
+            gap_y: self.gap_y,
+            margin: self.margin,
+            cell_border: self.cell_border,
+            grid_lines: self.grid_lines,
+            header_line: self.header_line,
+            line_style: self.line_style,
+            missing: self.missing,
+            max_label_lines: self.max_label_lines.map(NonZeroUsize::get),
+        })
+    }
+}
+
+fn heading(text: Option<String>, font_size: f32, alignment: LabelAlignment) -> Option<Heading> {
+    text.map(|text| Heading {
+        text,
+        font_size,
+        alignment,
+    })
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
-    
-    let config = PlotConfig {
-        images: args.images,
-        output: args.output,
-        rows: args.rows,
-        row_labels: args.row_labels,
-        column_labels: args.column_labels,
-        column_label_alignment: args.column_label_alignment.0,
-        row_label_alignment: args.row_label_alignment.0,
-        debug_mode: args.debug,
-        top_padding: args.top_padding,
-        left_padding: args.left_padding,
-    };
-
-    imx::xyplot::create_plot(&config)
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    let jobs = args
+        .jobs
+        .or_else(|| std::thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get);
+    let workers = Arc::new(Semaphore::new(jobs));
+
+    if let Some(manifest) = &args.batch {
+        return batch::run(manifest, &args, &matches, &workers).await;
+    }
+
+    if let Some(path) = args.config.clone() {
+        config::PlotFile::load(&path)?.apply(&mut args, &matches);
+    }
+
+    plot::create_plot(args.into_plot_config()?, &workers).await
 }
@@ -0,0 +1,83 @@
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Compares two strings so that runs of digits are ordered by their numeric
+/// value, e.g. `img2` sorts before `img10`.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a = a.chars().peekable();
+    let mut b = b.chars().peekable();
+
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(left), Some(right)) if left.is_ascii_digit() && right.is_ascii_digit() => {
+                let ordering = compare_digits(&take_digits(&mut a), &take_digits(&mut b));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(left), Some(right)) => {
+                let ordering = left.cmp(&right);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut Peekable<Chars<'_>>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+    digits
+}
+
+/// Compares two digit runs numerically without parsing, so arbitrarily long
+/// runs work. Equal values with more leading zeros sort last.
+fn compare_digits(a: &str, b: &str) -> Ordering {
+    let a_trimmed = a.trim_start_matches('0');
+    let b_trimmed = b.trim_start_matches('0');
+    a_trimmed
+        .len()
+        .cmp(&b_trimmed.len())
+        .then_with(|| a_trimmed.cmp(b_trimmed))
+        .then_with(|| a.len().cmp(&b.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_digit_runs_by_value() {
+        assert_eq!(natural_cmp("img2", "img10"), Ordering::Less);
+        assert_eq!(natural_cmp("img10", "img2"), Ordering::Greater);
+        assert_eq!(natural_cmp("step_9_cfg_10", "step_9_cfg_9"), Ordering::Greater);
+    }
+
+    #[test]
+    fn compares_text_by_character() {
+        assert_eq!(natural_cmp("a1", "b1"), Ordering::Less);
+        assert_eq!(natural_cmp("abc", "abc"), Ordering::Equal);
+        assert_eq!(natural_cmp("ab", "abc"), Ordering::Less);
+    }
+
+    #[test]
+    fn sorts_leading_zeros_after_equal_values() {
+        assert_eq!(natural_cmp("img007", "img7"), Ordering::Greater);
+        assert_eq!(natural_cmp("img007", "img8"), Ordering::Less);
+    }
+
+    #[test]
+    fn handles_runs_longer_than_any_integer() {
+        let long = "9".repeat(40);
+        assert_eq!(natural_cmp(&format!("x{long}"), &format!("x1{long}")), Ordering::Less);
+    }
+}
@@ -0,0 +1,216 @@
+use anyhow::{Context, Result, bail};
+use image::codecs::avif::AvifEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::codecs::tiff::TiffEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, ExtendedColorType, ImageEncoder, RgbaImage};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// JPEG quality used when `--quality` is not given, matching the encoder default.
+const DEFAULT_JPEG_QUALITY: u8 = 75;
+
+/// AVIF encoder speed from 1 (slowest, smallest) to 10 (fastest).
+const AVIF_SPEED: u8 = 4;
+
+/// AVIF quality used when `--quality` is not given.
+const DEFAULT_AVIF_QUALITY: u8 = 80;
+
+/// An image format the plot can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Jpeg,
+    Png,
+    WebP,
+    Avif,
+    Tiff,
+}
+
+impl OutputFormat {
+    fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            "webp" => Some(Self::WebP),
+            "avif" => Some(Self::Avif),
+            "tif" | "tiff" => Some(Self::Tiff),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpeg",
+            Self::Png => "png",
+            Self::WebP => "webp",
+            Self::Avif => "avif",
+            Self::Tiff => "tiff",
+        }
+    }
+
+    fn supports_alpha(self) -> bool {
+        self != Self::Jpeg
+    }
+
+    fn is_lossy(self) -> bool {
+        matches!(self, Self::Jpeg | Self::Avif)
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "jpeg" | "jpg" => Ok(Self::Jpeg),
+            "png" => Ok(Self::Png),
+            "webp" => Ok(Self::WebP),
+            "avif" => Ok(Self::Avif),
+            "tiff" | "tif" => Ok(Self::Tiff),
+            _ => Err(format!(
+                "Invalid format: {s}. Valid values are: jpeg, png, webp, avif, tiff"
+            )),
+        }
+    }
+}
+
+/// Trade-off between PNG encoding speed and file size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+impl FromStr for PngCompression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fast" => Ok(Self::Fast),
+            "default" => Ok(Self::Default),
+            "best" => Ok(Self::Best),
+            _ => Err(format!(
+                "Invalid PNG compression: {s}. Valid values are: fast, default, best"
+            )),
+        }
+    }
+}
+
+/// How the plot is encoded, with the format resolved and checked up front so
+/// that a mistake fails before any image is decoded.
+#[derive(Debug, Clone, Copy)]
+pub struct Encoding {
+    pub format: OutputFormat,
+    /// Quality from 1 to 100 for lossy formats
+    pub quality: Option<u8>,
+    pub png_compression: PngCompression,
+}
+
+impl Encoding {
+    /// Determines the format for `path`, inferred from its extension unless
+    /// given explicitly, and checks that the encoder settings and transparency
+    /// apply to it.
+    pub fn resolve(
+        path: &Path,
+        format: Option<OutputFormat>,
+        quality: Option<u8>,
+        png_compression: PngCompression,
+        transparent: bool,
+    ) -> Result<Self> {
+        let inferred = OutputFormat::from_extension(path);
+        let format = match (format, inferred) {
+            (Some(format), Some(inferred)) if format != inferred => bail!(
+                "Output extension of {} does not match --format {}",
+                path.display(),
+                format.name()
+            ),
+            (Some(format), _) | (None, Some(format)) => format,
+            (None, None) => bail!(
+                "Cannot infer the output format of {}; use a .jpg, .png, .webp, .avif or .tiff \
+                 extension or pass --format",
+                path.display()
+            ),
+        };
+
+        if let Some(quality) = quality.filter(|quality| !(1..=100).contains(quality)) {
+            bail!("Invalid quality: {quality}. Expected a value from 1 to 100");
+        }
+        if quality.is_some() && !format.is_lossy() {
+            bail!(
+                "--quality only applies to jpeg and avif output, not {}",
+                format.name()
+            );
+        }
+        if transparent && !format.supports_alpha() {
+            bail!(
+                "{} output cannot store transparency; use png, webp, avif or tiff instead",
+                format.name()
+            );
+        }
+        Ok(Self {
+            format,
+            quality,
+            png_compression,
+        })
+    }
+}
+
+/// Encodes the canvas according to `encoding`.
+///
+/// The alpha channel is only written for `transparent` canvases; opaque
+/// canvases are saved as RGB so non-transparent output is unchanged.
+pub fn save(canvas: RgbaImage, path: &Path, transparent: bool, encoding: Encoding) -> Result<()> {
+    let image = DynamicImage::ImageRgba8(canvas);
+    let image = if transparent {
+        image
+    } else {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+    };
+
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+    let (width, height) = (image.width(), image.height());
+    let color: ExtendedColorType = image.color().into();
+    let pixels = image.as_bytes();
+
+    match encoding.format {
+        OutputFormat::Jpeg => JpegEncoder::new_with_quality(
+            &mut writer,
+            encoding.quality.unwrap_or(DEFAULT_JPEG_QUALITY),
+        )
+        .write_image(pixels, width, height, color),
+        OutputFormat::Png => {
+            let compression = match encoding.png_compression {
+                PngCompression::Fast => CompressionType::Fast,
+                PngCompression::Default => CompressionType::Default,
+                PngCompression::Best => CompressionType::Best,
+            };
+            PngEncoder::new_with_quality(&mut writer, compression, FilterType::Adaptive)
+                .write_image(pixels, width, height, color)
+        }
+        OutputFormat::WebP => {
+            WebPEncoder::new_lossless(&mut writer).write_image(pixels, width, height, color)
+        }
+        OutputFormat::Avif => AvifEncoder::new_with_speed_quality(
+            &mut writer,
+            AVIF_SPEED,
+            encoding.quality.unwrap_or(DEFAULT_AVIF_QUALITY),
+        )
+        .write_image(pixels, width, height, color),
+        OutputFormat::Tiff => {
+            TiffEncoder::new(&mut writer).write_image(pixels, width, height, color)
+        }
+    }
+    .with_context(|| format!("Failed to encode {}", path.display()))?;
+
+    writer
+        .flush()
+        .with_context(|| format!("Failed to save plot to {}", path.display()))
+}
@@ -0,0 +1,633 @@
+use crate::captions::CaptionPosition;
+use crate::fit::{CellSize, Fit, FitMode, Resample};
+use crate::grid::Grid;
+use crate::labels::{self, LabelGroup};
+use crate::layout::{Area, Layout, Margin, Padding};
+use crate::limits::OutputLimits;
+use crate::lines::{self, LineStyle};
+use crate::loader::{self, Cell, MissingPolicy};
+use crate::output::{self, Encoding};
+use crate::text::{self, FontChain, LabelAlignment, Text};
+use crate::theme::Theme;
+use anyhow::{Result, bail};
+use image::{Pixel, Rgba, RgbaImage, imageops};
+use imageproc::drawing::{draw_filled_rect_mut, draw_hollow_rect_mut};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+
+/// Space between a caption and the edges of its band or overlay strip.
+const CAPTION_INSET: u32 = 4;
+
+/// Space around the title, subtitle and footer.
+const HEADING_INSET: u32 = 8;
+
+/// Opacity of the background-colored strip behind overlay captions, which
+/// keeps them legible while the image still shows through.
+const OVERLAY_STRIP_ALPHA: u8 = 192;
+
+/// How often the layout is re-planned to settle label wrapping after cells
+/// were shrunk to respect output size limits.
+const MAX_LAYOUT_PASSES: usize = 4;
+
+/// Cell size used when no input could be loaded to size the cells from.
+const PLACEHOLDER_SIZE: u32 = 256;
+
+/// Space between the note of a missing image and its dashed border.
+const PLACEHOLDER_INSET: u32 = 4;
+
+const DEBUG_IMAGE: Rgba<u8> = Rgba([173, 216, 230, 255]);
+const DEBUG_ROW_LABEL: Rgba<u8> = Rgba([255, 182, 193, 255]);
+const DEBUG_COLUMN_LABEL: Rgba<u8> = Rgba([144, 238, 144, 255]);
+const DEBUG_CAPTION: Rgba<u8> = Rgba([221, 160, 221, 255]);
+const DEBUG_GUTTER: Rgba<u8> = Rgba([255, 250, 160, 255]);
+const DEBUG_ROW_GROUP: Rgba<u8> = Rgba([240, 128, 128, 255]);
+const DEBUG_COLUMN_GROUP: Rgba<u8> = Rgba([60, 179, 113, 255]);
+const DEBUG_HEADING: Rgba<u8> = Rgba([255, 218, 185, 255]);
+
+/// Text drawn across the whole plot, above or below the grid.
+#[derive(Debug, Clone)]
+pub struct Heading {
+    pub text: String,
+    pub font_size: f32,
+    pub alignment: LabelAlignment,
+}
+
+impl Heading {
+    /// The heading broken into lines that fit a band of the given width.
+    fn wrapped(&self, fonts: &FontChain, width: u32) -> String {
+        Text::new(fonts, self.font_size).wrap(&self.text, label_width(width, HEADING_INSET), None)
+    }
+
+    /// Height of a band of the given width that holds the heading, including its inset.
+    fn band_height(&self, fonts: &FontChain, width: u32) -> u32 {
+        text_height(&Text::new(fonts, self.font_size), &self.wrapped(fonts, width)) + 2 * HEADING_INSET
+    }
+
+    fn draw(&self, canvas: &mut RgbaImage, area: Area, fonts: &FontChain, color: Rgba<u8>) {
+        Text::new(fonts, self.font_size).draw(
+            canvas,
+            &self.wrapped(fonts, area.width),
+            area.inset(HEADING_INSET),
+            self.alignment,
+            LabelAlignment::Center,
+            color,
+        );
+    }
+}
+
+/// Everything needed to render one plot.
+#[derive(Debug, Clone)]
+pub struct PlotConfig {
+    pub images: Vec<PathBuf>,
+    pub output: PathBuf,
+    pub grid: Grid,
+    pub row_labels: Vec<String>,
+    pub column_labels: Vec<String>,
+    pub column_label_alignment: LabelAlignment,
+    pub row_label_alignment: LabelAlignment,
+    pub debug_mode: bool,
+    pub top_padding: Padding,
+    pub left_padding: Padding,
+    /// Headers spanning consecutive columns, drawn above the column labels
+    pub column_groups: Vec<LabelGroup>,
+    /// Headers spanning consecutive rows, drawn left of the row labels
+    pub row_groups: Vec<LabelGroup>,
+    pub column_group_padding: Padding,
+    pub row_group_padding: Padding,
+    /// Space between labels and the edges of their bands
+    pub label_inset: u32,
+    pub title: Option<Heading>,
+    pub subtitle: Option<Heading>,
+    pub footer: Option<Heading>,
+    /// One caption per image, in image order. Missing entries are left blank.
+    pub captions: Vec<String>,
+    pub caption_alignment: LabelAlignment,
+    pub caption_position: CaptionPosition,
+    /// Primary font file, the built-in font if `None`
+    pub font: Option<PathBuf>,
+    /// Fonts tried in order for characters the primary font lacks
+    pub fallback_fonts: Vec<PathBuf>,
+    pub column_label_font_size: f32,
+    pub row_label_font_size: f32,
+    pub caption_font_size: f32,
+    pub theme: Theme,
+    pub encoding: Encoding,
+    /// Size every image is fitted into; the largest image size if `None`
+    pub cell_size: Option<CellSize>,
+    pub fit: FitMode,
+    /// Filter used whenever images are scaled
+    pub resample: Resample,
+    pub limits: OutputLimits,
+    pub gap_x: u32,
+    pub gap_y: u32,
+    pub margin: Margin,
+    /// Thickness of the border around each image cell, zero for none
+    pub cell_border: u32,
+    /// Thickness of the lines between rows and columns, zero for none
+    pub grid_lines: u32,
+    /// Thickness of the lines between the label bands and the images, zero for none
+    pub header_line: u32,
+    pub line_style: LineStyle,
+    pub missing: MissingPolicy,
+    /// Lines after which wrapped labels are cut off with an ellipsis
+    pub max_label_lines: Option<usize>,
+}
+
+/// Decodes and fits the input images concurrently, bounded by `workers`,
+/// then composites and saves the plot on the blocking thread pool.
+pub async fn create_plot(mut config: PlotConfig, workers: &Arc<Semaphore>) -> Result<()> {
+    let loaded = loader::load_images(&config.images, workers).await?;
+    let cells = collect_cells(&mut config, loaded)?;
+    let fonts = FontChain::load(config.font.as_deref(), &config.fallback_fonts)?;
+
+    let cell = config.cell_size.unwrap_or_else(|| {
+        let size = CellSize::enclosing(cells.iter().filter_map(Cell::image));
+        if size.width == 0 || size.height == 0 {
+            CellSize {
+                width: PLACEHOLDER_SIZE,
+                height: PLACEHOLDER_SIZE,
+            }
+        } else {
+            size
+        }
+    });
+    let mut layout = plan_layout(&config, &fonts, cell);
+
+    // Narrower cells can wrap column labels onto more lines, which takes
+    // space from the images again, so shrink until the layout fits.
+    let mut scale = 1.0;
+    for _ in 0..MAX_LAYOUT_PASSES {
+        let step = config.limits.scale(&layout)?;
+        if step >= 1.0 {
+            break;
+        }
+        scale *= step;
+        layout.scale_cells(step);
+        let scaled = CellSize {
+            width: layout.cell_width,
+            height: layout.cell_height,
+        };
+        layout = plan_layout(&config, &fonts, scaled);
+    }
+    layout.check()?;
+    config.limits.check(&layout)?;
+
+    let cells = if scale < 1.0 || config.cell_size.is_some() || config.fit != FitMode::None {
+        let fit = Fit {
+            cell: CellSize {
+                width: layout.cell_width,
+                height: layout.cell_height,
+            },
+            mode: config.fit,
+            scale,
+            filter: config.resample.0,
+        };
+        loader::fit_images(cells, workers, fit).await?
+    } else {
+        cells
+    };
+
+    tokio::task::spawn_blocking(move || render(&config, &cells, &layout, &fonts)).await?
+}
+
+/// Applies the missing image policy to the load results. Failures are
+/// reported on stderr unless they abort the plot; skipped inputs take their
+/// captions with them so the remaining captions stay with their images.
+fn collect_cells(config: &mut PlotConfig, loaded: Vec<Result<Cell>>) -> Result<Vec<Cell>> {
+    let failures: Vec<String> = loaded
+        .iter()
+        .filter_map(|result| result.as_ref().err())
+        .map(|err| format!("{err:#}"))
+        .collect();
+    if failures.is_empty() {
+        return Ok(loaded.into_iter().flatten().collect());
+    }
+
+    match config.missing {
+        MissingPolicy::Error => bail!(
+            "{} of {} images failed to load:\n  {}",
+            failures.len(),
+            loaded.len(),
+            failures.join("\n  ")
+        ),
+        MissingPolicy::Skip => {
+            for failure in &failures {
+                eprintln!("Warning: {failure}; skipped");
+            }
+            let mut captions = std::mem::take(&mut config.captions).into_iter();
+            let mut cells = Vec::new();
+            for result in loaded {
+                let caption = captions.next();
+                if let Ok(cell) = result {
+                    cells.push(cell);
+                    config.captions.extend(caption);
+                }
+            }
+            if cells.iter().all(|cell| cell.image().is_none()) {
+                bail!("None of the images could be loaded");
+            }
+            Ok(cells)
+        }
+        MissingPolicy::Placeholder => {
+            for failure in &failures {
+                eprintln!("Warning: {failure}; drawn as a placeholder");
+            }
+            Ok(loaded
+                .into_iter()
+                .zip(&config.images)
+                .map(|(result, path)| result.unwrap_or_else(|_| Cell::Missing(path.clone())))
+                .collect())
+        }
+    }
+}
+
+/// Computes the layout for cells of the given size.
+fn plan_layout(config: &PlotConfig, fonts: &FontChain, cell: CellSize) -> Layout {
+    let caption_text = Text::new(fonts, config.caption_font_size);
+    let column_text = Text::new(fonts, config.column_label_font_size);
+    let row_text = Text::new(fonts, config.row_label_font_size);
+    let inset = config.label_inset;
+
+    // Row labels only wrap in a band of fixed width; an automatic band grows to fit them.
+    let column_labels = wrap_labels(&column_text, &config.column_labels, label_width(cell.width, inset), config);
+    let row_width = match config.left_padding {
+        Padding::Fixed(width) => label_width(width, inset),
+        Padding::Auto => f32::INFINITY,
+    };
+    let row_labels = wrap_labels(&row_text, &config.row_labels, row_width, config);
+
+    let mut layout = Layout {
+        rows: config.grid.rows,
+        cols: config.grid.cols,
+        fill_order: config.grid.fill_order,
+        cell_width: cell.width,
+        cell_height: cell.height,
+        top_padding: band_size(config.top_padding, &column_labels, inset, |label| {
+            text_height(&column_text, label)
+        }),
+        left_padding: band_size(config.left_padding, &row_labels, inset, |label| {
+            text_width(&row_text, label)
+        }),
+        column_group_height: band_size(
+            config.column_group_padding,
+            &group_labels(&config.column_groups),
+            inset,
+            |label| text_height(&column_text, label),
+        ),
+        row_group_width: band_size(config.row_group_padding, &group_labels(&config.row_groups), inset, |label| {
+            text_width(&row_text, label)
+        }),
+        title_height: 0,
+        footer_height: 0,
+        caption_height: match config.caption_position {
+            CaptionPosition::Below => caption_band_height(&caption_text, &config.captions),
+            CaptionPosition::Overlay => 0,
+        },
+        gap_x: config.gap_x,
+        gap_y: config.gap_y,
+        margin: config.margin,
+    };
+
+    // Headings span the whole plot, so they wrap to its width once that is known.
+    // The layout is not checked yet, so measure it without overflowing.
+    let width = layout.width().saturating_sub(layout.margin.left).saturating_sub(layout.margin.right);
+    layout.title_height = [&config.title, &config.subtitle]
+        .into_iter()
+        .flatten()
+        .map(|heading| heading.band_height(fonts, width))
+        .sum();
+    layout.footer_height = config.footer.as_ref().map_or(0, |footer| footer.band_height(fonts, width));
+    layout
+}
+
+fn render(config: &PlotConfig, cells: &[Cell], layout: &Layout, fonts: &FontChain) -> Result<()> {
+    let caption_text = Text::new(fonts, config.caption_font_size);
+    let mut canvas =
+        RgbaImage::from_pixel(layout.width(), layout.height(), config.theme.background.0);
+    for ((row, col), cell) in layout.positions().zip(cells) {
+        let area = layout.cell(row, col);
+        match cell {
+            Cell::Image(image) => {
+                let (x, y) = area.centered(image.width(), image.height());
+                imageops::overlay(&mut canvas, image, i64::from(x), i64::from(y));
+            }
+            Cell::Missing(path) => draw_placeholder(&mut canvas, area, path, &caption_text, &config.theme),
+            Cell::Empty => {}
+        }
+    }
+
+    draw_lines(&mut canvas, config, layout, cells);
+    draw_labels(&mut canvas, config, layout, fonts);
+    draw_captions(&mut canvas, config, layout, &caption_text, cells.len());
+    draw_headings(&mut canvas, config, layout, fonts);
+
+    let transparent = !config.theme.background.is_opaque();
+    output::save(canvas, &config.output, transparent, config.encoding)?;
+
+    if config.debug_mode {
+        let debug = draw_debug(config, layout, cells);
+        output::save(debug, &debug_path(&config.output), transparent, config.encoding)?;
+    }
+
+    Ok(())
+}
+
+/// Marks a cell whose image failed to load with a dashed outline, the word
+/// "missing" and the file name.
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss, clippy::cast_sign_loss)]
+fn draw_placeholder(canvas: &mut RgbaImage, area: Area, path: &Path, text: &Text, theme: &Theme) {
+    lines::draw_border(canvas, area, 2, LineStyle::Dashed, theme.grid_line.0);
+    let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+    // Keep the note inside the cell, however narrow or short it is.
+    let inner = area.inset(PLACEHOLDER_INSET);
+    let width = inner.width as f32;
+    let max_lines = (inner.height as f32 / text.line_height()).floor().max(1.0) as usize;
+    let note = text.clip(&text.wrap(&format!("missing\n{name}"), width, Some(max_lines)), width);
+    text.draw(
+        canvas,
+        &note,
+        inner,
+        LabelAlignment::Center,
+        LabelAlignment::Center,
+        theme.text.0,
+    );
+}
+
+fn draw_lines(canvas: &mut RgbaImage, config: &PlotConfig, layout: &Layout, cells: &[Cell]) {
+    let color = config.theme.grid_line.0;
+    if config.grid_lines > 0 {
+        for line in layout.separators(config.grid_lines) {
+            lines::draw(canvas, line, config.line_style, color);
+        }
+    }
+    if config.header_line > 0 {
+        for line in layout.header_lines(config.header_line) {
+            lines::draw(canvas, line, config.line_style, color);
+        }
+    }
+    if config.cell_border > 0 {
+        let filled = layout.positions().zip(cells).filter(|(_, cell)| !matches!(cell, Cell::Empty));
+        for ((row, col), _) in filled {
+            lines::draw_border(canvas, layout.cell(row, col), config.cell_border, config.line_style, color);
+        }
+    }
+}
+
+fn draw_labels(canvas: &mut RgbaImage, config: &PlotConfig, layout: &Layout, fonts: &FontChain) {
+    let column_text = Text::new(fonts, config.column_label_font_size);
+    for (col, span, group) in labels::group_spans(&config.column_groups, layout.cols) {
+        column_text.draw(
+            canvas,
+            &group.label,
+            layout.column_group(col, span).inset(config.label_inset),
+            config.column_label_alignment,
+            LabelAlignment::Center,
+            config.theme.text.0,
+        );
+    }
+    let column_labels = wrap_labels(
+        &column_text,
+        &config.column_labels,
+        label_width(layout.cell_width, config.label_inset),
+        config,
+    );
+    for (col, label) in (0..layout.cols).zip(&column_labels) {
+        column_text.draw(
+            canvas,
+            label,
+            layout.column_label(col).inset(config.label_inset),
+            config.column_label_alignment,
+            LabelAlignment::Center,
+            config.theme.text.0,
+        );
+    }
+    let row_text = Text::new(fonts, config.row_label_font_size);
+    for (row, span, group) in labels::group_spans(&config.row_groups, layout.rows) {
+        row_text.draw(
+            canvas,
+            &group.label,
+            layout.row_group(row, span).inset(config.label_inset),
+            LabelAlignment::Center,
+            config.row_label_alignment,
+            config.theme.text.0,
+        );
+    }
+    let row_labels = wrap_labels(
+        &row_text,
+        &config.row_labels,
+        label_width(layout.left_padding, config.label_inset),
+        config,
+    );
+    for (row, label) in (0..layout.rows).zip(&row_labels) {
+        row_text.draw(
+            canvas,
+            label,
+            layout.row_label(row).inset(config.label_inset),
+            LabelAlignment::Center,
+            config.row_label_alignment,
+            config.theme.text.0,
+        );
+    }
+}
+
+fn draw_headings(canvas: &mut RgbaImage, config: &PlotConfig, layout: &Layout, fonts: &FontChain) {
+    let color = config.theme.text.0;
+    let mut band = layout.title();
+    for heading in [&config.title, &config.subtitle].into_iter().flatten() {
+        let height = heading.band_height(fonts, band.width);
+        heading.draw(canvas, Area { height, ..band }, fonts, color);
+        band.y += height;
+    }
+    if let Some(footer) = &config.footer {
+        footer.draw(canvas, layout.footer(), fonts, color);
+    }
+}
+
+fn draw_captions(
+    canvas: &mut RgbaImage,
+    config: &PlotConfig,
+    layout: &Layout,
+    text: &Text,
+    count: usize,
+) {
+    for ((row, col), caption) in layout.positions().zip(&config.captions).take(count) {
+        if caption.is_empty() {
+            continue;
+        }
+        let area = match config.caption_position {
+            CaptionPosition::Below => layout.caption(row, col),
+            CaptionPosition::Overlay => {
+                let strip = overlay_strip(layout.cell(row, col), text, caption);
+                shade(canvas, strip, config.theme.background.with_alpha(OVERLAY_STRIP_ALPHA).0);
+                strip
+            }
+        };
+        text.draw(
+            canvas,
+            caption,
+            area,
+            config.caption_alignment,
+            LabelAlignment::Center,
+            config.theme.text.0,
+        );
+    }
+}
+
+/// Blends `color` over the pixels of `area`, unlike a filled rectangle, which
+/// would replace them and cut a hole into images on a transparent canvas.
+fn shade(canvas: &mut RgbaImage, area: Area, color: Rgba<u8>) {
+    for y in area.y..(area.y + area.height).min(canvas.height()) {
+        for x in area.x..(area.x + area.width).min(canvas.width()) {
+            canvas.get_pixel_mut(x, y).blend(&color);
+        }
+    }
+}
+
+fn wrap_labels(text: &Text, labels: &[String], width: f32, config: &PlotConfig) -> Vec<String> {
+    labels
+        .iter()
+        .map(|label| text.wrap(label, width, config.max_label_lines))
+        .collect()
+}
+
+/// Width available to labels in a band of the given width.
+#[allow(clippy::cast_precision_loss)]
+fn label_width(band: u32, inset: u32) -> f32 {
+    band.saturating_sub(inset.saturating_mul(2)) as f32
+}
+
+fn group_labels(groups: &[LabelGroup]) -> Vec<&str> {
+    groups.iter().map(|group| group.label.as_str()).collect()
+}
+
+/// Size of a label band along the axis `measure` measures. Bands without
+/// labels take no space.
+fn band_size<S: AsRef<str>>(padding: Padding, labels: &[S], inset: u32, measure: impl Fn(&str) -> u32) -> u32 {
+    if labels.is_empty() {
+        return 0;
+    }
+    match padding {
+        Padding::Fixed(size) => size,
+        Padding::Auto => {
+            let widest = labels.iter().map(|label| measure(label.as_ref())).max().unwrap_or(0);
+            widest.saturating_add(inset.saturating_mul(2))
+        }
+    }
+}
+
+/// Width of the longest line of a possibly multiline block of text.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn text_width(text: &Text, content: &str) -> u32 {
+    text::lines(content)
+        .map(|line| text.line_width(line).ceil() as u32)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Height of a possibly multiline block of text.
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss, clippy::cast_sign_loss)]
+fn text_height(text: &Text, content: &str) -> u32 {
+    (text.line_height() * text::lines(content).count() as f32).ceil() as u32
+}
+
+/// Height of the band that fits the tallest caption, or zero without captions.
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss, clippy::cast_sign_loss)]
+fn caption_band_height(text: &Text, captions: &[String]) -> u32 {
+    let lines = captions.iter().map(|caption| text::lines(caption).count()).max();
+    match lines {
+        Some(lines) if captions.iter().any(|caption| !caption.is_empty()) => {
+            (text.line_height() * lines as f32).ceil() as u32 + 2 * CAPTION_INSET
+        }
+        _ => 0,
+    }
+}
+
+/// The strip along the bottom of a cell that an overlaid caption is drawn in.
+#[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss, clippy::cast_sign_loss)]
+fn overlay_strip(cell: Area, text: &Text, caption: &str) -> Area {
+    let height = ((text.line_height() * text::lines(caption).count() as f32).ceil() as u32
+        + 2 * CAPTION_INSET)
+        .min(cell.height);
+    Area {
+        y: cell.y + cell.height - height,
+        height,
+        ..cell
+    }
+}
+
+/// Draws the layout with each kind of area filled in its own color on the
+/// theme background, outlined in the theme's grid line color. Gutters are
+/// filled without an outline so narrow gaps stay visible.
+fn draw_debug(config: &PlotConfig, layout: &Layout, cells: &[Cell]) -> RgbaImage {
+    let theme = &config.theme;
+    let mut canvas = RgbaImage::from_pixel(layout.width(), layout.height(), theme.background.0);
+    for gutter in layout.gutters() {
+        if let Some(rect) = gutter.rect() {
+            draw_filled_rect_mut(&mut canvas, rect, DEBUG_GUTTER);
+        }
+    }
+    let mut fill_area = |area: Area, color: Rgba<u8>| {
+        if let Some(rect) = area.rect() {
+            draw_filled_rect_mut(&mut canvas, rect, color);
+            draw_hollow_rect_mut(&mut canvas, rect, theme.grid_line.0);
+        }
+    };
+
+    if layout.title_height > 0 {
+        fill_area(layout.title(), DEBUG_HEADING);
+    }
+    if layout.footer_height > 0 {
+        fill_area(layout.footer(), DEBUG_HEADING);
+    }
+    if layout.column_group_height > 0 {
+        for (col, span, _) in labels::group_spans(&config.column_groups, layout.cols) {
+            fill_area(layout.column_group(col, span), DEBUG_COLUMN_GROUP);
+        }
+    }
+    if layout.row_group_width > 0 {
+        for (row, span, _) in labels::group_spans(&config.row_groups, layout.rows) {
+            fill_area(layout.row_group(row, span), DEBUG_ROW_GROUP);
+        }
+    }
+    if layout.top_padding > 0 {
+        for col in 0..layout.cols {
+            fill_area(layout.column_label(col), DEBUG_COLUMN_LABEL);
+        }
+    }
+    if layout.left_padding > 0 {
+        for row in 0..layout.rows {
+            fill_area(layout.row_label(row), DEBUG_ROW_LABEL);
+        }
+    }
+    for ((row, col), cell) in layout.positions().zip(cells) {
+        let area = match cell {
+            Cell::Image(image) => {
+                let (x, y) = layout.cell(row, col).centered(image.width(), image.height());
+                Area {
+                    x,
+                    y,
+                    width: image.width(),
+                    height: image.height(),
+                }
+            }
+            Cell::Missing(_) => layout.cell(row, col),
+            Cell::Empty => continue,
+        };
+        fill_area(area, DEBUG_IMAGE);
+        if layout.caption_height > 0 {
+            fill_area(layout.caption(row, col), DEBUG_CAPTION);
+        }
+    }
+
+    canvas
+}
+
+/// `output.jpg` becomes `output_debug.jpg`.
+fn debug_path(output: &Path) -> PathBuf {
+    let stem = output.file_stem().unwrap_or_default().to_string_lossy();
+    match output.extension() {
+        Some(ext) => output.with_file_name(format!("{stem}_debug.{}", ext.to_string_lossy())),
+        None => output.with_file_name(format!("{stem}_debug")),
+    }
+}
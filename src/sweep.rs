@@ -0,0 +1,227 @@
+use crate::loader;
+use crate::natural::natural_cmp;
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Placeholders recognised in sweep templates, in the order they are tried.
+const PLACEHOLDERS: [&str; 2] = ["{x}", "{y}"];
+
+/// A grid discovered from the file names in a sweep directory.
+#[derive(Debug)]
+pub struct SweepGrid {
+    /// Images in row-major order, with empty slots for combinations that
+    /// have no file
+    pub images: Vec<PathBuf>,
+    pub rows: u32,
+    pub row_labels: Vec<String>,
+    pub column_labels: Vec<String>,
+}
+
+/// Compiles a sweep pattern into a regex matched against file names.
+///
+/// A pattern containing `{x}` or `{y}` is a template in which everything else
+/// is literal, e.g. `seed_{x}_cfg_{y}.png`. Any other pattern is used as a
+/// regex with named `x` and/or `y` groups.
+pub fn compile_pattern(pattern: &str) -> Result<Regex> {
+    let source = if PLACEHOLDERS.iter().any(|p| pattern.contains(p)) {
+        template_to_regex(pattern)
+    } else {
+        pattern.to_string()
+    };
+    let regex = Regex::new(&source).with_context(|| format!("Invalid sweep pattern: {pattern}"))?;
+
+    if !regex.capture_names().flatten().any(|name| name == "x" || name == "y") {
+        bail!("Sweep pattern must capture at least one of {{x}} or {{y}}: {pattern}");
+    }
+    Ok(regex)
+}
+
+fn template_to_regex(template: &str) -> String {
+    let mut source = String::from("^");
+    let mut rest = template;
+
+    while let Some((index, placeholder)) = PLACEHOLDERS
+        .iter()
+        .filter_map(|p| rest.find(p).map(|index| (index, *p)))
+        .min()
+    {
+        source.push_str(&regex::escape(&rest[..index]));
+        let _ = write!(source, "(?P<{}>.+?)", &placeholder[1..2]);
+        rest = &rest[index + placeholder.len()..];
+    }
+
+    source.push_str(&regex::escape(rest));
+    source.push('$');
+    source
+}
+
+/// Scans `dir` for files whose names match `pattern` and arranges them into a
+/// grid with one column per captured `x` value and one row per `y` value.
+pub fn discover(dir: &Path, pattern: &Regex) -> Result<SweepGrid> {
+    let has_group = |group: &str| pattern.capture_names().flatten().any(|name| name == group);
+    let (has_x, has_y) = (has_group("x"), has_group("y"));
+
+    let mut cells: HashMap<(String, String), PathBuf> = HashMap::new();
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read sweep directory {}", dir.display()))?;
+
+    for entry in entries {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(captures) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| pattern.captures(name))
+        else {
+            continue;
+        };
+
+        let capture = |group: &str| captures.name(group).map_or_else(String::new, |m| m.as_str().to_string());
+        let key = (capture("x"), capture("y"));
+        if let Some(previous) = cells.insert(key.clone(), path.clone()) {
+            bail!(
+                "Both {} and {} match x={}, y={}",
+                previous.display(),
+                path.display(),
+                key.0,
+                key.1
+            );
+        }
+    }
+
+    if cells.is_empty() {
+        bail!("No files in {} match the sweep pattern", dir.display());
+    }
+
+    let xs = sorted_values(cells.keys().map(|(x, _)| x));
+    let ys = sorted_values(cells.keys().map(|(_, y)| y));
+
+    let mut images = Vec::with_capacity(xs.len() * ys.len());
+    for y in &ys {
+        for x in &xs {
+            images.push(cells.remove(&(x.clone(), y.clone())).unwrap_or_else(loader::empty_slot));
+        }
+    }
+
+    Ok(SweepGrid {
+        images,
+        rows: u32::try_from(ys.len()).context("Too many rows in sweep")?,
+        row_labels: if has_y { ys } else { Vec::new() },
+        column_labels: if has_x { xs } else { Vec::new() },
+    })
+}
+
+/// Deduplicates captured values and sorts them numerically when every value
+/// is a plain decimal number, falling back to natural ordering otherwise.
+fn sorted_values<'a>(values: impl Iterator<Item = &'a String>) -> Vec<String> {
+    let mut values: Vec<String> = values.cloned().collect();
+    values.sort_unstable();
+    values.dedup();
+
+    let numbers: Option<Vec<f64>> = values.iter().map(|value| parse_decimal(value)).collect();
+    if let Some(numbers) = numbers {
+        let mut pairs: Vec<(f64, String)> = numbers.into_iter().zip(values).collect();
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| natural_cmp(&a.1, &b.1)));
+        pairs.into_iter().map(|(_, value)| value).collect()
+    } else {
+        values.sort_by(|a, b| natural_cmp(a, b));
+        values
+    }
+}
+
+/// Parses numbers such as `7`, `-0.5` or `1.25`, but not `inf`, `NaN` or
+/// exponents, which are more likely parts of a name than sweep values.
+fn parse_decimal(value: &str) -> Option<f64> {
+    let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    if whole.len() + fraction.len() == 0 || !is_digits(whole) || !is_digits(fraction) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn sweep_dir(names: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        for name in names {
+            File::create(dir.path().join(name)).unwrap();
+        }
+        dir
+    }
+
+    fn file_names(grid: &SweepGrid) -> Vec<String> {
+        grid.images
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn templates_match_literally() {
+        let regex = compile_pattern("seed_{x}_cfg_{y}.png").unwrap();
+        let captures = regex.captures("seed_12_cfg_7.5.png").unwrap();
+        assert_eq!(&captures["x"], "12");
+        assert_eq!(&captures["y"], "7.5");
+        assert!(!regex.is_match("seed_12_cfg_7.5xpng"));
+    }
+
+    #[test]
+    fn regex_patterns_need_a_named_group() {
+        assert!(compile_pattern(r"^(?P<x>\d+)\.png$").is_ok());
+        assert!(compile_pattern(r"^(\d+)\.png$").is_err());
+        assert!(compile_pattern("(?P<x>").is_err());
+    }
+
+    #[test]
+    fn discovers_grid_in_numeric_order() {
+        let dir = sweep_dir(&[
+            "seed_10_cfg_1.png",
+            "seed_2_cfg_1.png",
+            "seed_10_cfg_2.png",
+            "seed_2_cfg_2.png",
+            "notes.txt",
+        ]);
+        let grid = discover(dir.path(), &compile_pattern("seed_{x}_cfg_{y}.png").unwrap()).unwrap();
+        assert_eq!(grid.rows, 2);
+        assert_eq!(grid.column_labels, ["2", "10"]);
+        assert_eq!(grid.row_labels, ["1", "2"]);
+        assert_eq!(
+            file_names(&grid),
+            ["seed_2_cfg_1.png", "seed_10_cfg_1.png", "seed_2_cfg_2.png", "seed_10_cfg_2.png"]
+        );
+    }
+
+    #[test]
+    fn missing_combinations_become_empty_slots() {
+        let dir = sweep_dir(&["a_1.png", "b_1.png", "a_2.png"]);
+        let grid = discover(dir.path(), &compile_pattern("{x}_{y}.png").unwrap()).unwrap();
+        assert_eq!(grid.images.len(), 4);
+        assert!(loader::is_empty_slot(&grid.images[3]));
+        assert_eq!(file_names(&grid)[..3], ["a_1.png", "b_1.png", "a_2.png"]);
+    }
+
+    #[test]
+    fn rejects_ambiguous_and_empty_sweeps() {
+        let dir = sweep_dir(&["a_1.png", "a_1.jpg"]);
+        assert!(discover(dir.path(), &compile_pattern(r"^(?P<x>\w)_(?P<y>\d)").unwrap()).is_err());
+        assert!(discover(dir.path(), &compile_pattern("{x}.webp").unwrap()).is_err());
+    }
+
+    #[test]
+    fn only_plain_decimals_sort_numerically() {
+        let values = ["10", "9", "-1.5"].map(String::from);
+        assert_eq!(sorted_values(values.iter()), ["-1.5", "9", "10"]);
+        let values = ["inf", "1e3", "NaN", "2"].map(String::from);
+        assert_eq!(sorted_values(values.iter()), ["1e3", "2", "NaN", "inf"]);
+    }
+}
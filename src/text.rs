@@ -0,0 +1,329 @@
+use crate::layout::Area;
+use ab_glyph::{Font, FontArc, PxScale, ScaleFont};
+use anyhow::{Context, Result};
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::draw_text_mut;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const FONT_DATA: &[u8] = include_bytes!("../assets/DejaVuSans.ttf");
+
+/// Default pixel height of row and column label text.
+pub const LABEL_FONT_SIZE: f32 = 24.0;
+
+/// Default pixel height of per-image caption text.
+pub const CAPTION_FONT_SIZE: f32 = 18.0;
+
+/// Default pixel height of the plot title.
+pub const TITLE_FONT_SIZE: f32 = 40.0;
+
+/// Default pixel height of the plot subtitle.
+pub const SUBTITLE_FONT_SIZE: f32 = 28.0;
+
+/// Default pixel height of the plot footer.
+pub const FOOTER_FONT_SIZE: f32 = 18.0;
+
+/// Where text is placed within the space available to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelAlignment {
+    /// Left, or top for row labels
+    Start,
+    Center,
+    /// Right, or bottom for row labels
+    End,
+}
+
+impl FromStr for LabelAlignment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "start" => Ok(Self::Start),
+            "center" => Ok(Self::Center),
+            "end" => Ok(Self::End),
+            _ => Err(format!("Invalid alignment: {s}. Valid values are: start, center, end")),
+        }
+    }
+}
+
+/// Splits a label into lines at newlines and at literal `\n` escapes, so
+/// labels typed in a shell as `"Title\nSubtitle"` render on two lines.
+pub fn lines(text: &str) -> impl Iterator<Item = &str> {
+    text.split('\n').flat_map(|line| line.split("\\n"))
+}
+
+/// Appended to labels cut off after the maximum number of lines.
+const ELLIPSIS: char = '\u{2026}';
+
+/// An ordered list of fonts. Each character is drawn with the first font that
+/// has a glyph for it, so fallbacks can cover scripts and emoji the primary
+/// font lacks. The built-in font always ends the chain.
+#[derive(Clone)]
+pub struct FontChain {
+    fonts: Vec<FontArc>,
+}
+
+impl FontChain {
+    pub fn load(primary: Option<&Path>, fallbacks: &[PathBuf]) -> Result<Self> {
+        let mut fonts = primary
+            .into_iter()
+            .chain(fallbacks.iter().map(PathBuf::as_path))
+            .map(load_font)
+            .collect::<Result<Vec<_>>>()?;
+        fonts.push(FontArc::try_from_slice(FONT_DATA).context("Failed to load built-in font")?);
+        Ok(Self { fonts })
+    }
+
+    fn primary(&self) -> &FontArc {
+        &self.fonts[0]
+    }
+
+    fn font_for(&self, c: char) -> usize {
+        self.fonts
+            .iter()
+            .position(|font| font.glyph_id(c).0 != 0)
+            .unwrap_or(0)
+    }
+
+    /// Splits a line into runs of consecutive characters drawn with the same font.
+    fn runs<'t>(&self, line: &'t str) -> Vec<(&FontArc, &'t str)> {
+        let mut runs = Vec::new();
+        let mut start = 0;
+        let mut current = None;
+        for (index, c) in line.char_indices() {
+            let font = self.font_for(c);
+            if let Some(previous) = current.filter(|&previous| previous != font) {
+                runs.push((&self.fonts[previous], &line[start..index]));
+                start = index;
+            }
+            current = Some(font);
+        }
+        if let Some(font) = current {
+            runs.push((&self.fonts[font], &line[start..]));
+        }
+        runs
+    }
+}
+
+fn load_font(path: &Path) -> Result<FontArc> {
+    let data = std::fs::read(path).with_context(|| format!("Failed to read font {}", path.display()))?;
+    FontArc::try_from_vec(data).with_context(|| format!("Failed to parse font {}", path.display()))
+}
+
+/// Measures and draws label text at one size.
+pub struct Text {
+    fonts: FontChain,
+    scale: PxScale,
+}
+
+impl Text {
+    pub fn new(fonts: &FontChain, size: f32) -> Self {
+        Self {
+            fonts: fonts.clone(),
+            scale: PxScale::from(size),
+        }
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.fonts.primary().as_scaled(self.scale).height()
+    }
+
+    pub fn line_width(&self, line: &str) -> f32 {
+        self.fonts
+            .runs(line)
+            .into_iter()
+            .map(|(font, run)| run_width(font, self.scale, run))
+            .sum()
+    }
+
+    /// Breaks a label into lines no wider than `width` at spaces, keeping its
+    /// own line breaks. A word wider than `width` gets a line to itself. With
+    /// `max_lines`, later lines are dropped and the last one kept ends in an
+    /// ellipsis.
+    pub fn wrap(&self, text: &str, width: f32, max_lines: Option<usize>) -> String {
+        let mut wrapped: Vec<String> = Vec::new();
+        for line in lines(text) {
+            let mut current = String::new();
+            for word in line.split(' ').filter(|word| !word.is_empty()) {
+                let candidate = if current.is_empty() {
+                    word.to_string()
+                } else {
+                    format!("{current} {word}")
+                };
+                if current.is_empty() || self.line_width(&candidate) <= width {
+                    current = candidate;
+                } else {
+                    wrapped.push(std::mem::replace(&mut current, word.to_string()));
+                }
+            }
+            wrapped.push(current);
+        }
+
+        if let Some(max_lines) = max_lines.filter(|&max_lines| wrapped.len() > max_lines) {
+            wrapped.truncate(max_lines);
+            if let Some(last) = wrapped.last_mut() {
+                *last = self.ellipsize(last, width);
+            }
+        }
+        wrapped.join("\n")
+    }
+
+    /// Ellipsizes every line of `text` that is wider than `width`, such as a
+    /// word that [`Text::wrap`] could not break.
+    pub fn clip(&self, text: &str, width: f32) -> String {
+        text.lines()
+            .map(|line| {
+                if self.line_width(line) <= width {
+                    line.to_string()
+                } else {
+                    self.ellipsize(line, width)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Shortens a line until it fits `width` with an ellipsis appended.
+    fn ellipsize(&self, line: &str, width: f32) -> String {
+        let mut line = line.trim_end().to_string();
+        loop {
+            let candidate = format!("{line}{ELLIPSIS}");
+            if line.is_empty() || self.line_width(&candidate) <= width {
+                return candidate;
+            }
+            line.pop();
+            line.truncate(line.trim_end().len());
+        }
+    }
+
+    /// Draws a possibly multiline label inside `area`. Each line is aligned
+    /// horizontally and the block of lines is aligned vertically.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    pub fn draw(
+        &self,
+        canvas: &mut RgbaImage,
+        text: &str,
+        area: Area,
+        horizontal: LabelAlignment,
+        vertical: LabelAlignment,
+        color: Rgba<u8>,
+    ) {
+        if area.width == 0 || area.height == 0 {
+            return;
+        }
+
+        let line_height = self.line_height();
+        let ascent = self.fonts.primary().as_scaled(self.scale).ascent();
+        let block_height = line_height * lines(text).count() as f32;
+        let mut y = area.y as f32 + offset(vertical, area.height as f32, block_height);
+
+        for line in lines(text) {
+            let mut x = area.x as f32 + offset(horizontal, area.width as f32, self.line_width(line));
+            for (font, run) in self.fonts.runs(line) {
+                // Line up baselines when a fallback font has a different ascent.
+                let baseline_shift = ascent - font.as_scaled(self.scale).ascent();
+                draw_text_mut(
+                    canvas,
+                    color,
+                    x.round() as i32,
+                    (y + baseline_shift).round() as i32,
+                    self.scale,
+                    font,
+                    run,
+                );
+                x += run_width(font, self.scale, run);
+            }
+            y += line_height;
+        }
+    }
+}
+
+fn run_width(font: &FontArc, scale: PxScale, run: &str) -> f32 {
+    let font = font.as_scaled(scale);
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in run.chars() {
+        let id = font.glyph_id(c);
+        if let Some(previous) = previous {
+            width += font.kern(previous, id);
+        }
+        width += font.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+/// Distance from the start of the available space at which content of
+/// length `used` begins for the given alignment.
+fn offset(alignment: LabelAlignment, available: f32, used: f32) -> f32 {
+    match alignment {
+        LabelAlignment::Start => 0.0,
+        LabelAlignment::Center => (available - used) / 2.0,
+        LabelAlignment::End => available - used,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text() -> Text {
+        Text::new(&FontChain::load(None, &[]).unwrap(), LABEL_FONT_SIZE)
+    }
+
+    #[test]
+    fn wraps_long_labels_at_spaces() {
+        let text = text();
+        let width = text.line_width("sampler euler");
+        let wrapped = text.wrap("sampler euler steps 20 cfg 7", width, None);
+        assert!(wrapped.lines().count() > 1);
+        assert!(wrapped.lines().all(|line| text.line_width(line) <= width));
+        assert_eq!(wrapped.replace('\n', " "), "sampler euler steps 20 cfg 7");
+    }
+
+    #[test]
+    fn keeps_explicit_line_breaks() {
+        assert_eq!(text().wrap("a\\nb", 1000.0, None), "a\nb");
+    }
+
+    #[test]
+    fn gives_over_wide_words_their_own_line() {
+        let text = text();
+        let width = text.line_width("a b");
+        assert_eq!(text.wrap("a extraordinarily b", width, None), "a\nextraordinarily\nb");
+    }
+
+    #[test]
+    fn truncates_to_max_lines_with_an_ellipsis() {
+        let text = text();
+        let width = text.line_width("one two");
+        let wrapped = text.wrap("one two three four five six", width, Some(2));
+        let lines: Vec<_> = wrapped.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "one two");
+        assert!(lines[1].ends_with(ELLIPSIS));
+        assert!(text.line_width(lines[1]) <= width);
+    }
+
+    #[test]
+    fn clips_only_lines_wider_than_the_width() {
+        let text = text();
+        let width = text.line_width("short");
+        let clipped = text.clip("short\nextraordinarily", width);
+        let lines: Vec<_> = clipped.lines().collect();
+        assert_eq!(lines[0], "short");
+        assert!(lines[1].ends_with(ELLIPSIS));
+        assert!(text.line_width(lines[1]) <= width);
+    }
+
+    #[test]
+    fn ellipsizes_over_wide_words_within_the_width() {
+        let text = text();
+        let width = text.line_width("abc");
+        let line = text.ellipsize("extraordinarily", width);
+        assert!(line.ends_with(ELLIPSIS));
+        assert!(text.line_width(&line) <= width);
+        let fits = format!("ab{ELLIPSIS}");
+        assert_eq!(text.ellipsize("ab", text.line_width(&fits)), fits);
+    }
+}
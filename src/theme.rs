@@ -0,0 +1,178 @@
+use image::Rgba;
+use std::str::FromStr;
+
+/// A color given as `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa`, `rgb(r, g, b)`,
+/// `rgba(r, g, b, a)` with alpha from 0 to 1, or a color name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color(pub Rgba<u8>);
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self(Rgba([r, g, b, 255]))
+    }
+
+    /// The same color with a different alpha value.
+    pub const fn with_alpha(self, alpha: u8) -> Self {
+        let [r, g, b, _] = self.0.0;
+        Self(Rgba([r, g, b, alpha]))
+    }
+
+    pub const fn is_opaque(self) -> bool {
+        self.0.0[3] == u8::MAX
+    }
+
+    fn named(name: &str) -> Option<Self> {
+        let color = match name {
+            "white" => Self::new(255, 255, 255),
+            "black" => Self::new(0, 0, 0),
+            "gray" | "grey" => Self::new(128, 128, 128),
+            "lightgray" | "lightgrey" => Self::new(211, 211, 211),
+            "darkgray" | "darkgrey" => Self::new(64, 64, 64),
+            "red" => Self::new(255, 0, 0),
+            "green" => Self::new(0, 128, 0),
+            "blue" => Self::new(0, 0, 255),
+            "yellow" => Self::new(255, 255, 0),
+            "cyan" => Self::new(0, 255, 255),
+            "magenta" => Self::new(255, 0, 255),
+            "orange" => Self::new(255, 165, 0),
+            "purple" => Self::new(128, 0, 128),
+            "transparent" => Self(Rgba([0, 0, 0, 0])),
+            _ => return None,
+        };
+        Some(color)
+    }
+
+    fn hex(digits: &str) -> Option<Self> {
+        let channel = |i: usize, width: usize| {
+            let value = u8::from_str_radix(digits.get(i * width..(i + 1) * width)?, 16).ok()?;
+            // A single hex digit is shorthand for the digit repeated, e.g. f -> ff.
+            Some(if width == 1 { value * 17 } else { value })
+        };
+        let width = match digits.len() {
+            3 | 4 => 1,
+            6 | 8 => 2,
+            _ => return None,
+        };
+        let alpha = if digits.len() / width == 4 { channel(3, width)? } else { 255 };
+        Some(Self(Rgba([channel(0, width)?, channel(1, width)?, channel(2, width)?, alpha])))
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn functional(text: &str) -> Option<Self> {
+        let (name, rest) = text.split_once('(')?;
+        let values: Vec<&str> = rest.strip_suffix(')')?.split(',').map(str::trim).collect();
+        let channel = |value: &str| value.parse::<u8>().ok();
+        match (name.trim(), values.as_slice()) {
+            ("rgb", &[red, green, blue]) => Some(Self::new(channel(red)?, channel(green)?, channel(blue)?)),
+            ("rgba", &[red, green, blue, alpha]) => {
+                let alpha = alpha.parse::<f32>().ok().filter(|alpha| (0.0..=1.0).contains(alpha))?;
+                Some(Self(Rgba([
+                    channel(red)?,
+                    channel(green)?,
+                    channel(blue)?,
+                    (alpha * 255.0).round() as u8,
+                ])))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_lowercase();
+        let color = match lower.strip_prefix('#') {
+            Some(digits) => Self::hex(digits),
+            None => Self::named(&lower).or_else(|| Self::functional(&lower)),
+        };
+        color.ok_or_else(|| {
+            format!("Invalid color: {s}. Use #rrggbb, #rrggbbaa, rgb(r, g, b), rgba(r, g, b, a) or a color name")
+        })
+    }
+}
+
+/// Colors used for the plot background, text and lines.
+#[derive(Debug, Clone, Copy)]
+pub struct Theme {
+    pub background: Color,
+    pub text: Color,
+    pub grid_line: Color,
+}
+
+/// A built-in theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThemeName {
+    /// Black text on white
+    #[default]
+    Light,
+    /// Light text on a dark gray background
+    Dark,
+}
+
+impl ThemeName {
+    pub fn theme(self) -> Theme {
+        match self {
+            Self::Light => Theme {
+                background: Color::new(255, 255, 255),
+                text: Color::new(0, 0, 0),
+                grid_line: Color::new(64, 64, 64),
+            },
+            Self::Dark => Theme {
+                background: Color::new(30, 30, 30),
+                text: Color::new(230, 230, 230),
+                grid_line: Color::new(110, 110, 110),
+            },
+        }
+    }
+}
+
+impl FromStr for ThemeName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "light" => Ok(Self::Light),
+            "dark" => Ok(Self::Dark),
+            _ => Err(format!("Invalid theme: {s}. Valid values are: light, dark")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgba(text: &str) -> [u8; 4] {
+        text.parse::<Color>().unwrap().0.0
+    }
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(rgba("#ff8000"), [255, 128, 0, 255]);
+        assert_eq!(rgba("#FF800080"), [255, 128, 0, 128]);
+        assert_eq!(rgba("#f80"), [255, 136, 0, 255]);
+        assert_eq!(rgba("#f808"), [255, 136, 0, 136]);
+    }
+
+    #[test]
+    fn parses_functional_colors() {
+        assert_eq!(rgba("rgb(1, 2, 3)"), [1, 2, 3, 255]);
+        assert_eq!(rgba(" RGBA(1,2,3,0.5) "), [1, 2, 3, 128]);
+        assert_eq!(rgba("rgba(1, 2, 3, 0)"), [1, 2, 3, 0]);
+    }
+
+    #[test]
+    fn parses_named_colors() {
+        assert_eq!(rgba("white"), [255, 255, 255, 255]);
+        assert_eq!(rgba("Black"), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn rejects_invalid_colors() {
+        for text in ["", "#", "#12345", "#ggg", "rgb(1, 2)", "rgb(256, 0, 0)", "rgba(1, 2, 3, 1.5)", "nope"] {
+            assert!(text.parse::<Color>().is_err(), "{text} should be rejected");
+        }
+    }
+}
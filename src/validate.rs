@@ -0,0 +1,122 @@
+use crate::Args;
+use crate::grid::{FillOrder, Grid};
+use anyhow::{Result, bail};
+
+/// Finds mismatches between the grid shape, the images and the labels. Each
+/// one still renders, but rarely the way it was meant to.
+pub fn check(args: &Args, grid: Grid) -> Vec<String> {
+    let mut issues = Vec::new();
+    let count = u32::try_from(args.images.len()).unwrap_or(u32::MAX);
+
+    let (lines, length, line) = match grid.fill_order {
+        FillOrder::Row => (grid.rows, grid.cols, "row"),
+        FillOrder::Column => (grid.cols, grid.rows, "column"),
+    };
+    let filled = count.div_ceil(length);
+    if filled < lines {
+        issues.push(format!(
+            "{lines} {line}s of {length} were requested but {count} images only fill {filled}"
+        ));
+    } else if count % length != 0 {
+        issues.push(format!(
+            "{count} images do not divide evenly into {line}s of {length}; the last {line} has {}",
+            count % length
+        ));
+    }
+
+    for (labels, expected, axis) in [
+        (args.column_labels.len(), grid.cols, "column"),
+        (args.row_labels.len(), grid.rows, "row"),
+    ] {
+        if labels > 0 && labels != expected as usize {
+            issues.push(format!("{labels} {axis} labels were given for {expected} {axis}s"));
+        }
+    }
+
+    for (groups, expected, axis) in [
+        (&args.column_groups, grid.cols, "column"),
+        (&args.row_groups, grid.rows, "row"),
+    ] {
+        let spanned: u64 = groups.iter().map(|group| u64::from(group.span)).sum();
+        if spanned > u64::from(expected) {
+            issues.push(format!("The {axis} groups span {spanned} {axis}s but the grid has {expected}"));
+        }
+    }
+
+    issues
+}
+
+/// Fails with every issue at once, or prints them as warnings if `lenient`.
+pub fn report(issues: &[String], lenient: bool) -> Result<()> {
+    if issues.is_empty() {
+        return Ok(());
+    }
+    if !lenient {
+        bail!("{}\nPass --lenient to render anyway", issues.join("\n"));
+    }
+    for issue in issues {
+        eprintln!("Warning: {issue}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn issues(flags: &[&str], rows: u32, cols: u32, fill_order: FillOrder) -> Vec<String> {
+        let args = Args::try_parse_from(["xyplot"].iter().chain(flags)).unwrap();
+        check(&args, Grid { rows, cols, fill_order })
+    }
+
+    #[test]
+    fn accepts_a_full_grid() {
+        assert!(issues(&["a", "b", "c", "d", "--column-labels", "x", "y"], 2, 2, FillOrder::Row).is_empty());
+    }
+
+    #[test]
+    fn reports_a_ragged_last_row() {
+        assert_eq!(
+            issues(&["a", "b", "c"], 2, 2, FillOrder::Row),
+            ["3 images do not divide evenly into rows of 2; the last row has 1"]
+        );
+    }
+
+    #[test]
+    fn reports_rows_left_empty() {
+        assert_eq!(
+            issues(&["a", "b"], 3, 2, FillOrder::Row),
+            ["3 rows of 2 were requested but 2 images only fill 1"]
+        );
+    }
+
+    #[test]
+    fn follows_the_fill_order() {
+        assert_eq!(
+            issues(&["a", "b", "c"], 2, 2, FillOrder::Column),
+            ["3 images do not divide evenly into columns of 2; the last column has 1"]
+        );
+        assert_eq!(
+            issues(&["a", "b"], 2, 3, FillOrder::Column),
+            ["3 columns of 2 were requested but 2 images only fill 1"]
+        );
+    }
+
+    #[test]
+    fn reports_label_count_mismatches() {
+        assert_eq!(
+            issues(&["a", "b", "c", "d", "--row-labels", "x", "y", "z"], 2, 2, FillOrder::Row),
+            ["3 row labels were given for 2 rows"]
+        );
+    }
+
+    #[test]
+    fn reports_groups_running_past_the_grid() {
+        assert_eq!(
+            issues(&["a", "b", "--column-groups", "A:4294967295", "B:2"], 1, 2, FillOrder::Row),
+            ["The column groups span 4294967297 columns but the grid has 2"]
+        );
+        assert!(issues(&["a", "b", "--column-groups", "A:1", "B:1"], 1, 2, FillOrder::Row).is_empty());
+    }
+}